@@ -0,0 +1,694 @@
+//! Derive macros for the traits in `arrows::monad`, re-exported from there.
+//!
+//! Both derives work over the last type parameter of a struct or enum. Fields
+//! of that type are mapped (or visited) directly, fields that don't mention it
+//! are left alone, and fields like `Vec<T>`, `Option<T>`, `Box<T>`, tuples or
+//! the type itself are recursed into.
+
+use proc_macro::TokenStream;
+use proc_macro2::{TokenStream as TokenStream2, TokenTree};
+use quote::{format_ident, quote, ToTokens};
+use syn::{
+    parse_macro_input, spanned::Spanned, Data, DeriveInput, Fields, GenericArgument, GenericParam,
+    Ident, PathArguments, Type,
+};
+
+/// Generates a `{Name}Family` type with `Kind` and `FunctorFamily` impls,
+/// and the `Hkt` impl that gives the type `fmap` over its last parameter.
+///
+/// The family also gets `FunctorRefFamily` and `FunctorMutFamily` impls,
+/// which hold as long as the fields that don't mention the parameter are
+/// `Clone` (for `fmap_ref`), and every type wrapping it has the same impl.
+#[proc_macro_derive(Functor)]
+pub fn derive_functor(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    Target::new(&input, "Functor")
+        .and_then(|target| target.functor())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+/// Generates a `Foldable` impl visiting every value of the last type
+/// parameter, in field order.
+#[proc_macro_derive(Foldable)]
+pub fn derive_foldable(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    Target::new(&input, "Foldable")
+        .and_then(|target| target.foldable())
+        .unwrap_or_else(syn::Error::into_compile_error)
+        .into()
+}
+
+struct Target<'a> {
+    input: &'a DeriveInput,
+    // the parameter being mapped over
+    param: &'a Ident,
+    derive: &'static str,
+}
+
+// A pattern binding every field of a variant, and the constructor to rebuild
+// it from expressions in the same order.
+struct Shape<'a> {
+    path: TokenStream2,
+    fields: &'a Fields,
+}
+
+impl<'a> Target<'a> {
+    fn new(input: &'a DeriveInput, derive: &'static str) -> syn::Result<Self> {
+        let param = input.generics.type_params().last().ok_or_else(|| {
+            let msg = format!(
+                "cannot derive {} for a type without type parameters",
+                derive
+            );
+            syn::Error::new(input.ident.span(), msg)
+        })?;
+        if !param.bounds.is_empty() {
+            let msg = format!(
+                "cannot derive {}: `{}` must accept any type, so it can't have bounds",
+                derive, param.ident
+            );
+            return Err(syn::Error::new(param.span(), msg));
+        }
+        if let Some(clause) = &input.generics.where_clause {
+            if mentions(clause.to_token_stream(), &param.ident) {
+                let msg = format!(
+                    "cannot derive {}: `{}` must accept any type, so it can't have bounds",
+                    derive, param.ident
+                );
+                return Err(syn::Error::new(clause.span(), msg));
+            }
+        }
+        Ok(Self {
+            input,
+            param: &param.ident,
+            derive,
+        })
+    }
+
+    // the type with its last parameter replaced by `arg`
+    fn ty_with(&self, arg: TokenStream2) -> TokenStream2 {
+        let name = &self.input.ident;
+        let args = self.input.generics.params.iter().map(|param| match param {
+            GenericParam::Lifetime(l) => l.lifetime.to_token_stream(),
+            GenericParam::Type(t) if &t.ident == self.param => arg.clone(),
+            GenericParam::Type(t) => t.ident.to_token_stream(),
+            GenericParam::Const(c) => c.ident.to_token_stream(),
+        });
+        quote!(#name<#(#args),*>)
+    }
+
+    // every parameter but the one being mapped over
+    fn other_params(&self) -> impl Iterator<Item = &GenericParam> {
+        self.input
+            .generics
+            .params
+            .iter()
+            .filter(|param| !matches!(param, GenericParam::Type(t) if &t.ident == self.param))
+    }
+
+    fn other_args(&self) -> Vec<TokenStream2> {
+        self.other_params()
+            .map(|param| match param {
+                GenericParam::Lifetime(l) => l.lifetime.to_token_stream(),
+                GenericParam::Type(t) => t.ident.to_token_stream(),
+                GenericParam::Const(c) => c.ident.to_token_stream(),
+            })
+            .collect()
+    }
+
+    fn shapes(&self) -> Vec<Shape<'a>> {
+        let name = &self.input.ident;
+        match &self.input.data {
+            Data::Struct(data) => vec![Shape {
+                path: quote!(#name),
+                fields: &data.fields,
+            }],
+            Data::Enum(data) => data
+                .variants
+                .iter()
+                .map(|variant| {
+                    let variant_name = &variant.ident;
+                    Shape {
+                        path: quote!(#name::#variant_name),
+                        fields: &variant.fields,
+                    }
+                })
+                .collect(),
+            Data::Union(_) => Vec::new(),
+        }
+    }
+
+    fn check_data(&self) -> syn::Result<()> {
+        match &self.input.data {
+            Data::Union(data) => {
+                let msg = format!("cannot derive {} for a union", self.derive);
+                Err(syn::Error::new(data.union_token.span(), msg))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn functor(&self) -> syn::Result<TokenStream2> {
+        self.check_data()?;
+        let vis = &self.input.vis;
+        let param = self.param;
+        let family = format_ident!("{}Family", self.input.ident);
+        let (impl_generics, _, where_clause) = self.input.generics.split_for_impl();
+        let self_ty = self.ty_with(quote!(#param));
+        let mapped_ty = self.ty_with(quote!(__B));
+        let family_ty = self.ty_with(quote!(__T));
+        let other_args = self.other_args();
+        let other_params: Vec<_> = self.other_params().collect();
+        // mentioning the type itself gives the family the same implied
+        // bounds, like `L: 'a` for a field of type `&'a L`
+        let phantom = self.ty_with(quote!(()));
+        let go_params = self.input.generics.params.iter();
+        let go_where = where_clause.map(|clause| &clause.predicates);
+
+        let arms = self
+            .shapes()
+            .into_iter()
+            .map(|shape| {
+                let (pattern, names) = shape.pattern();
+                let mapped = shape
+                    .fields
+                    .iter()
+                    .zip(&names)
+                    .map(|(field, name)| self.map_field(&field.ty, quote!(#name)))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                let built = shape.build(mapped);
+                Ok(quote!(#pattern => #built,))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        let mut ref_bounds = Vec::new();
+        let ref_arms = self
+            .shapes()
+            .into_iter()
+            .map(|shape| {
+                let (pattern, names) = shape.pattern();
+                let mapped = shape
+                    .fields
+                    .iter()
+                    .zip(&names)
+                    .map(|(field, name)| {
+                        self.map_ref_field(&field.ty, quote!(#name), &mut ref_bounds)
+                    })
+                    .collect::<syn::Result<Vec<_>>>()?;
+                let built = shape.build(mapped);
+                Ok(quote!(#pattern => #built,))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        let mut mut_bounds = Vec::new();
+        let mut_arms = self
+            .shapes()
+            .into_iter()
+            .map(|shape| {
+                let (pattern, names) = shape.pattern();
+                let mut updates = Vec::new();
+                for (field, name) in shape.fields.iter().zip(&names) {
+                    updates.extend(self.mut_field(&field.ty, quote!(#name), &mut mut_bounds)?);
+                }
+                Ok(quote!(#pattern => { #(#updates)* }))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        // the bounds are behind a binder, so that one on a concrete field
+        // type, like `Cell<u8>: Clone`, leaves the impl out rather than
+        // failing to compile
+        let own_bounds: Vec<_> = where_clause
+            .map(|clause| {
+                clause
+                    .predicates
+                    .iter()
+                    .map(ToTokens::to_token_stream)
+                    .collect()
+            })
+            .unwrap_or_default();
+        let ref_bounds: Vec<_> = own_bounds
+            .iter()
+            .cloned()
+            .chain(ref_bounds.iter().map(|bound| quote!(for<'__x> #bound)))
+            .collect();
+        let mut_bounds: Vec<_> = own_bounds
+            .iter()
+            .cloned()
+            .chain(mut_bounds.iter().map(|bound| quote!(for<'__x> #bound)))
+            .collect();
+        let go_params_ref = self.input.generics.params.iter();
+        let go_params_mut = self.input.generics.params.iter();
+
+        Ok(quote! {
+            #vis struct #family<#(#other_params),*>(::core::marker::PhantomData<#phantom>);
+
+            impl<#(#other_params),*> ::arrows::monad::Kind for #family<#(#other_args),*>
+            #where_clause
+            {
+                type M<__T> = #family_ty;
+            }
+
+            impl #impl_generics ::arrows::monad::Hkt<#param> for #self_ty #where_clause {
+                type Kind = #family<#(#other_args),*>;
+            }
+
+            impl<'__a, #(#other_params),*> ::arrows::monad::FunctorFamily<'__a>
+            for #family<#(#other_args),*>
+            #where_clause
+            {
+                fn fmap<#param, __B, __F>(fa: #self_ty, f: __F) -> #mapped_ty
+                where
+                    __F: Fn(#param) -> __B + '__a,
+                    #param: '__a,
+                    __B: '__a,
+                {
+                    // recursive fields go through `go`, so that every level
+                    // shares the same `&__F` instead of nesting closures
+                    #[allow(unused_variables)]
+                    fn go<#(#go_params,)* __B, __F>(this: #self_ty, f: &__F) -> #mapped_ty
+                    where
+                        __F: Fn(#param) -> __B,
+                        #go_where
+                    {
+                        match this {
+                            #(#arms)*
+                        }
+                    }
+                    go(fa, &f)
+                }
+            }
+
+            impl<'__a, #(#other_params),*> ::arrows::monad::FunctorRefFamily<'__a>
+            for #family<#(#other_args),*>
+            where
+                #(#ref_bounds,)*
+            {
+                fn fmap_ref<'__r, #param, __B, __F>(fa: &'__r #self_ty, mut f: __F) -> #mapped_ty
+                where
+                    __F: FnMut(&'__r #param) -> __B,
+                    #param: '__r,
+                {
+                    #[allow(unused_variables)]
+                    fn go<'__r, #(#go_params_ref,)* __B, __F>(
+                        this: &'__r #self_ty,
+                        f: &mut __F,
+                    ) -> #mapped_ty
+                    where
+                        __F: FnMut(&'__r #param) -> __B,
+                        #(#ref_bounds,)*
+                    {
+                        match this {
+                            #(#ref_arms)*
+                        }
+                    }
+                    go(fa, &mut f)
+                }
+            }
+
+            impl<'__a, #(#other_params),*> ::arrows::monad::FunctorMutFamily<'__a>
+            for #family<#(#other_args),*>
+            where
+                #(#mut_bounds,)*
+            {
+                fn fmap_in_place<#param, __F>(fa: &mut #self_ty, mut f: __F)
+                where
+                    __F: FnMut(&mut #param) + '__a,
+                    #param: '__a,
+                {
+                    #[allow(unused_variables)]
+                    fn go<#(#go_params_mut,)* __F>(this: &mut #self_ty, f: &mut __F)
+                    where
+                        __F: FnMut(&mut #param),
+                        #(#mut_bounds,)*
+                    {
+                        match this {
+                            #(#mut_arms)*
+                        }
+                    }
+                    go(fa, &mut f)
+                }
+            }
+        })
+    }
+
+    fn foldable(&self) -> syn::Result<TokenStream2> {
+        self.check_data()?;
+        let param = self.param;
+        let (impl_generics, _, where_clause) = self.input.generics.split_for_impl();
+        let self_ty = self.ty_with(quote!(#param));
+        let go_params = self.input.generics.params.iter();
+        let go_where = where_clause.map(|clause| &clause.predicates);
+
+        let arms = self
+            .shapes()
+            .into_iter()
+            .map(|shape| {
+                let (pattern, names) = shape.pattern();
+                let mut visits = Vec::new();
+                for (field, name) in shape.fields.iter().zip(&names) {
+                    visits.extend(self.fold_field(&field.ty, quote!(#name))?);
+                }
+                let body = match visits.is_empty() {
+                    true => quote!(acc),
+                    false => quote!({
+                        let mut acc = acc;
+                        #(#visits)*
+                        acc
+                    }),
+                };
+                Ok(quote!(#pattern => #body,))
+            })
+            .collect::<syn::Result<Vec<_>>>()?;
+
+        Ok(quote! {
+            impl #impl_generics ::arrows::monad::Foldable<#param> for #self_ty #where_clause {
+                fn fold_left<'__a, __Acc, __F>(&'__a self, init: __Acc, mut f: __F) -> __Acc
+                where
+                    __F: FnMut(__Acc, &'__a #param) -> __Acc,
+                    #param: '__a,
+                {
+                    #[allow(unused_variables)]
+                    fn go<'__a, #(#go_params,)* __Acc, __F>(
+                        this: &'__a #self_ty,
+                        acc: __Acc,
+                        f: &mut __F,
+                    ) -> __Acc
+                    where
+                        __F: FnMut(__Acc, &'__a #param) -> __Acc,
+                        #go_where
+                    {
+                        match this {
+                            #(#arms)*
+                        }
+                    }
+                    go(self, init, &mut f)
+                }
+            }
+        })
+    }
+
+    fn is_self(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Path(path) if path.qself.is_none() => path
+                .path
+                .segments
+                .last()
+                .is_some_and(|seg| seg.ident == self.input.ident || seg.ident == "Self"),
+            _ => false,
+        }
+    }
+
+    fn is_param(&self, ty: &Type) -> bool {
+        matches!(ty, Type::Path(path) if path.qself.is_none() && path.path.is_ident(self.param))
+    }
+
+    // `Wrapper<.., U>` where only `U` mentions the parameter
+    fn inner<'t>(&self, ty: &'t Type) -> Option<(&'t Ident, &'t Type)> {
+        let Type::Path(path) = ty else { return None };
+        if path.qself.is_some() {
+            return None;
+        }
+        let segments = &path.path.segments;
+        let last = segments.last()?;
+        if segments
+            .iter()
+            .rev()
+            .skip(1)
+            .any(|seg| !seg.arguments.is_none())
+        {
+            return None;
+        }
+        let PathArguments::AngleBracketed(args) = &last.arguments else {
+            return None;
+        };
+        let inner = args.args.last()?;
+        let others = args.args.iter().take(args.args.len() - 1);
+        if others
+            .map(ToTokens::to_token_stream)
+            .any(|arg| mentions(arg, self.param))
+        {
+            return None;
+        }
+        match inner {
+            GenericArgument::Type(inner) => Some((&last.ident, inner)),
+            _ => None,
+        }
+    }
+
+    fn unsupported(&self, ty: &Type) -> syn::Error {
+        let msg = format!(
+            "cannot derive {}: fields may only hold `{}` directly, in a tuple or `Box`, or as \
+             the last parameter of another {}",
+            self.derive, self.param, self.derive
+        );
+        syn::Error::new(ty.span(), msg)
+    }
+
+    // an expression mapping `value` of type `ty`
+    fn map_field(&self, ty: &Type, value: TokenStream2) -> syn::Result<TokenStream2> {
+        if !mentions(ty.to_token_stream(), self.param) {
+            return Ok(value);
+        }
+        match ty {
+            Type::Paren(paren) => self.map_field(&paren.elem, value),
+            Type::Group(group) => self.map_field(&group.elem, value),
+            Type::Tuple(tuple) => {
+                let names: Vec<_> = (0..tuple.elems.len())
+                    .map(|i| format_ident!("__x{}", i))
+                    .collect();
+                let mapped = tuple
+                    .elems
+                    .iter()
+                    .zip(&names)
+                    .map(|(ty, name)| self.map_field(ty, quote!(#name)))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Ok(quote!({
+                    let (#(#names,)*) = #value;
+                    (#(#mapped,)*)
+                }))
+            }
+            _ if self.is_param(ty) => Ok(quote!(f(#value))),
+            _ if self.is_self(ty) => Ok(quote!(go(#value, f))),
+            _ => match self.inner(ty) {
+                Some((wrapper, _)) if wrapper == "PhantomData" => {
+                    Ok(quote!(::core::marker::PhantomData))
+                }
+                Some((wrapper, inner)) if wrapper == "Box" => {
+                    let mapped = self.map_field(inner, quote!(*__x))?;
+                    Ok(quote!({
+                        let __x = #value;
+                        // `Box<T>: From<T>`, without naming `std` or `alloc`
+                        ::core::convert::From::from(#mapped)
+                    }))
+                }
+                Some((_, inner)) => {
+                    let mapped = self.map_field(inner, quote!(__y))?;
+                    Ok(quote!(::arrows::monad::Functor::fmap(#value, |__y| #mapped)))
+                }
+                None => Err(self.unsupported(ty)),
+            },
+        }
+    }
+
+    // an expression mapping `value`, a reference to a `ty`, and the bounds
+    // that needs
+    fn map_ref_field(
+        &self,
+        ty: &Type,
+        value: TokenStream2,
+        bounds: &mut Vec<TokenStream2>,
+    ) -> syn::Result<TokenStream2> {
+        if !mentions(ty.to_token_stream(), self.param) {
+            bounds.push(quote!(#ty: ::core::clone::Clone));
+            return Ok(quote!(::core::clone::Clone::clone(#value)));
+        }
+        match ty {
+            Type::Paren(paren) => self.map_ref_field(&paren.elem, value, bounds),
+            Type::Group(group) => self.map_ref_field(&group.elem, value, bounds),
+            Type::Tuple(tuple) => {
+                let names: Vec<_> = (0..tuple.elems.len())
+                    .map(|i| format_ident!("__x{}", i))
+                    .collect();
+                let mapped = tuple
+                    .elems
+                    .iter()
+                    .zip(&names)
+                    .map(|(ty, name)| self.map_ref_field(ty, quote!(#name), bounds))
+                    .collect::<syn::Result<Vec<_>>>()?;
+                Ok(quote!({
+                    let (#(#names,)*) = #value;
+                    (#(#mapped,)*)
+                }))
+            }
+            _ if self.is_param(ty) => Ok(quote!(f(#value))),
+            _ if self.is_self(ty) => Ok(quote!(go(#value, f))),
+            _ => match self.inner(ty) {
+                Some((wrapper, _)) if wrapper == "PhantomData" => {
+                    Ok(quote!(::core::marker::PhantomData))
+                }
+                Some((wrapper, inner)) if wrapper == "Box" => {
+                    let mapped = self.map_ref_field(inner, quote!(&**__x), bounds)?;
+                    Ok(quote!({
+                        let __x = #value;
+                        ::core::convert::From::from(#mapped)
+                    }))
+                }
+                Some((_, inner)) => {
+                    let family = self.family_of(ty);
+                    bounds.push(quote!(#family: ::arrows::monad::FunctorRefFamily<'__x>));
+                    let mapped = self.map_ref_field(inner, quote!(__y), bounds)?;
+                    Ok(quote!(::arrows::monad::FunctorRef::fmap_ref(#value, |__y| #mapped)))
+                }
+                None => Err(self.unsupported(ty)),
+            },
+        }
+    }
+
+    // statements updating `value`, a mutable reference to a `ty`, and the
+    // bounds they need
+    fn mut_field(
+        &self,
+        ty: &Type,
+        value: TokenStream2,
+        bounds: &mut Vec<TokenStream2>,
+    ) -> syn::Result<Option<TokenStream2>> {
+        if !mentions(ty.to_token_stream(), self.param) {
+            return Ok(None);
+        }
+        let update = match ty {
+            Type::Paren(paren) => return self.mut_field(&paren.elem, value, bounds),
+            Type::Group(group) => return self.mut_field(&group.elem, value, bounds),
+            Type::Tuple(tuple) => {
+                let names: Vec<_> = (0..tuple.elems.len())
+                    .map(|i| format_ident!("__x{}", i))
+                    .collect();
+                let mut updates = Vec::new();
+                for (ty, name) in tuple.elems.iter().zip(&names) {
+                    updates.extend(self.mut_field(ty, quote!(#name), bounds)?);
+                }
+                quote!({
+                    let (#(#names,)*) = #value;
+                    #(#updates)*
+                })
+            }
+            _ if self.is_param(ty) => quote!(f(#value);),
+            _ if self.is_self(ty) => quote!(go(#value, f);),
+            _ => match self.inner(ty) {
+                Some((wrapper, _)) if wrapper == "PhantomData" => return Ok(None),
+                Some((wrapper, inner)) if wrapper == "Box" => {
+                    return self.mut_field(inner, quote!(&mut **#value), bounds)
+                }
+                Some((_, inner)) => {
+                    let family = self.family_of(ty);
+                    bounds.push(quote!(#family: ::arrows::monad::FunctorMutFamily<'__x>));
+                    let update = self.mut_field(inner, quote!(__y), bounds)?;
+                    quote! {
+                        ::arrows::monad::FunctorMut::fmap_in_place(#value, |__y| {
+                            #update
+                        });
+                    }
+                }
+                None => return Err(self.unsupported(ty)),
+            },
+        };
+        Ok(Some(update))
+    }
+
+    // the family of `Wrapper<.., U>`, named without the parameter in `U`
+    fn family_of(&self, ty: &Type) -> TokenStream2 {
+        let mut unit = ty.clone();
+        if let Type::Path(path) = &mut unit {
+            if let Some(PathArguments::AngleBracketed(args)) =
+                path.path.segments.last_mut().map(|seg| &mut seg.arguments)
+            {
+                if let Some(last) = args.args.last_mut() {
+                    *last = GenericArgument::Type(syn::parse_quote!(()));
+                }
+            }
+        }
+        quote!(<#unit as ::arrows::monad::Hkt<()>>::Kind)
+    }
+
+    // statements folding `value`, a reference to a `ty`, into `acc`
+    fn fold_field(&self, ty: &Type, value: TokenStream2) -> syn::Result<Option<TokenStream2>> {
+        if !mentions(ty.to_token_stream(), self.param) {
+            return Ok(None);
+        }
+        let visit = match ty {
+            Type::Paren(paren) => return self.fold_field(&paren.elem, value),
+            Type::Group(group) => return self.fold_field(&group.elem, value),
+            Type::Tuple(tuple) => {
+                let names: Vec<_> = (0..tuple.elems.len())
+                    .map(|i| format_ident!("__x{}", i))
+                    .collect();
+                let mut visits = Vec::new();
+                for (ty, name) in tuple.elems.iter().zip(&names) {
+                    visits.extend(self.fold_field(ty, quote!(#name))?);
+                }
+                quote!({
+                    let (#(#names,)*) = #value;
+                    #(#visits)*
+                })
+            }
+            _ if self.is_param(ty) => quote!(acc = f(acc, #value);),
+            _ if self.is_self(ty) => quote!(acc = go(#value, acc, f);),
+            _ => match self.inner(ty) {
+                Some((wrapper, _)) if wrapper == "PhantomData" => return Ok(None),
+                Some((wrapper, inner)) if wrapper == "Box" => {
+                    return self.fold_field(inner, quote!(&**#value))
+                }
+                Some((_, inner)) => {
+                    let visit = self.fold_field(inner, quote!(__y))?;
+                    quote! {
+                        acc = ::arrows::monad::Foldable::fold_left(#value, acc, |acc, __y| {
+                            let mut acc = acc;
+                            #visit
+                            acc
+                        });
+                    }
+                }
+                None => return Err(self.unsupported(ty)),
+            },
+        };
+        Ok(Some(visit))
+    }
+}
+
+impl Shape<'_> {
+    // binds fields as `__f0, __f1, ..`
+    fn pattern(&self) -> (TokenStream2, Vec<Ident>) {
+        let path = &self.path;
+        let names: Vec<_> = (0..self.fields.len())
+            .map(|i| format_ident!("__f{}", i))
+            .collect();
+        let pattern = match self.fields {
+            Fields::Named(fields) => {
+                let idents = fields.named.iter().map(|field| &field.ident);
+                quote!(#path { #(#idents: #names),* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#names),*)),
+            Fields::Unit => quote!(#path),
+        };
+        (pattern, names)
+    }
+
+    fn build(&self, values: Vec<TokenStream2>) -> TokenStream2 {
+        let path = &self.path;
+        match self.fields {
+            Fields::Named(fields) => {
+                let idents = fields.named.iter().map(|field| &field.ident);
+                quote!(#path { #(#idents: #values),* })
+            }
+            Fields::Unnamed(_) => quote!(#path(#(#values),*)),
+            Fields::Unit => quote!(#path),
+        }
+    }
+}
+
+fn mentions(tokens: TokenStream2, ident: &Ident) -> bool {
+    tokens.into_iter().any(|token| match token {
+        TokenTree::Ident(i) => &i == ident,
+        TokenTree::Group(group) => mentions(group.stream(), ident),
+        _ => false,
+    })
+}
@@ -0,0 +1,131 @@
+//! Allocations and time per step of composed circuits.
+//!
+//! Run with `cargo bench --bench steps`. Building a circuit allocates once
+//! per stage, but stepping it should never allocate, so the run fails if any
+//! allocation is counted between steps.
+
+use arrows::{
+    arrow::*,
+    impls::{
+        circuit::{arrow, Circuit},
+        either::Either,
+        static_circuit::{arr, StaticCircuit},
+    },
+};
+use std::{
+    alloc::{GlobalAlloc, Layout, System},
+    hint::black_box,
+    sync::atomic::{AtomicUsize, Ordering},
+    time::Instant,
+};
+
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        unsafe { System.alloc(layout) }
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        unsafe { System.dealloc(ptr, layout) }
+    }
+}
+
+#[global_allocator]
+static GLOBAL: Counting = Counting;
+
+const STAGES: usize = 20;
+const STEPS: usize = 100_000;
+
+fn stateless<'a>() -> Circuit<'a, u64, u64> {
+    (0..STAGES).fold(arrow(|x| x), |circ, i| {
+        let stage = arrow(move |x: u64| x.wrapping_mul(31).wrapping_add(i as u64));
+        circ.then(stage)
+    })
+}
+
+fn accumulating<'a>() -> Circuit<'a, u64, u64> {
+    (0..STAGES).fold(arrow(|x| x), |circ, _| {
+        circ.then(Circuit::accum_dup(0, |x: u64, acc: u64| {
+            acc.wrapping_add(x)
+        }))
+    })
+}
+
+// a mix of fst, dup, left and loop_d
+fn combinators<'a>() -> Circuit<'a, u64, u64> {
+    (0..STAGES / 4).fold(arrow(|x| x), |circ, _| {
+        let parity = arrow(|x: u64| Either::from((x.is_multiple_of(2), x)));
+        let collatz = arrow(|x: u64| 3 * x + 1).owise(arrow(|x: u64| x / 2));
+        let count = arrow(|(x, n): (u64, u64)| (x + n, n + 1)).loop_d(0);
+        circ.then(parity)
+            .then(collatz)
+            .then(arrow(|x: u64| x).dup(arrow(|x: u64| x + 1)))
+            .then(count.fst())
+            .then_pure(|(a, b): (u64, u64)| a ^ b)
+    })
+}
+
+// the same pipeline as `stateless`, with its structure in the type
+fn static_stateless() -> impl StaticCircuit<u64, Output = u64> {
+    let s = |i: u64| arr(move |x: u64| x.wrapping_mul(31).wrapping_add(i));
+    s(0).then(s(1))
+        .then(s(2))
+        .then(s(3))
+        .then(s(4))
+        .then(s(5))
+        .then(s(6))
+        .then(s(7))
+        .then(s(8))
+        .then(s(9))
+        .then(s(10))
+        .then(s(11))
+        .then(s(12))
+        .then(s(13))
+        .then(s(14))
+        .then(s(15))
+        .then(s(16))
+        .then(s(17))
+        .then(s(18))
+        .then(s(19))
+}
+
+fn hand_written(x: u64) -> u64 {
+    (0..STAGES as u64).fold(x, |x, i| x.wrapping_mul(31).wrapping_add(i))
+}
+
+fn bench(name: &str, mut step: impl FnMut(u64) -> u64) {
+    // the first step may touch lazily initialized state
+    black_box(step(1));
+
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    let start = Instant::now();
+    for x in 0..STEPS as u64 {
+        black_box(step(black_box(x)));
+    }
+    let elapsed = start.elapsed();
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+
+    println!(
+        "{name:<12} {:>8.1} ns/step {:>6.2} allocations/step",
+        elapsed.as_nanos() as f64 / STEPS as f64,
+        allocations as f64 / STEPS as f64,
+    );
+    assert_eq!(allocations, 0, "{name} allocated while stepping");
+}
+
+fn main() {
+    let mut circ = stateless();
+    bench("stateless", |x| circ.step(x));
+    let mut circ = static_stateless();
+    bench("static", |x| circ.step(x));
+    bench("hand-written", hand_written);
+
+    let mut circ = accumulating();
+    bench("accum", |x| circ.step(x));
+    let mut circ = combinators();
+    bench("combinators", |x| circ.step(x));
+}
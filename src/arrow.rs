@@ -1,149 +1,499 @@
 use crate::impls::either::Either;
 
-pub trait CatFamily {
-    type M<T, U>: Category<T, U, CFamily = Self>;
+// The arrow traits follow the same pattern as `monad.rs`: each arrow type is
+// named by a single family, the operations live on the family traits
+// (CatFamily ⊂ ArrowFamily ⊂ ChoiceFamily), and Category, Arrow and
+// ArrowChoice give method syntax to every `Morphism` whose family implements
+// them. Results of `left` are therefore known to support `fst`, and so on.
+//
+// Every trait carries the lifetime `'a` that all inputs, outputs and captured
+// functions must outlive, so arrows are not limited to `'static` data.
+//
+// Some arrow types only keep functions and state with a given bound, e.g.
+// `Send` for `SendCircuit`. `Holds` says which values a family keeps, and
+// the methods that store a value of the caller's require it.
 
-    fn id<A>() -> Self::M<A, A>;
+/// An arrow type applied to an input and output, e.g. `Circuit<'a, A, B>` is
+/// `Morphism<'a, A, B>` with `Family = CircuitFamily`.
+pub trait Morphism<'a, A: 'a, B: 'a> {
+    type Family: CatFamily<'a, M<A, B> = Self>;
 }
 
-pub trait Category<A, B> {
-    type CFamily: CatFamily<M<A, B> = Self>;
+/// The arrow type of the family `K` from `A` to `B`.
+pub type ArrOf<'a, K, A, B> = <K as CatFamily<'a>>::M<A, B>;
 
+fn swap<A, B>((a, b): (A, B)) -> (B, A) {
+    (b, a)
+}
+
+fn merge<A>(either: Either<A, A>) -> A {
+    match either {
+        Either::Left(a) | Either::Right(a) => a,
+    }
+}
+
+pub trait CatFamily<'a> {
+    type M<A, B>: Morphism<'a, A, B, Family = Self>
+    where
+        A: 'a,
+        B: 'a;
+
+    fn id<A>() -> Self::M<A, A>
+    where
+        A: 'a;
+
+    // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
+    fn then<A, B, C>(producer: Self::M<A, B>, consumer: Self::M<B, C>) -> Self::M<A, C>
+    where
+        A: 'a,
+        B: 'a,
+        C: 'a;
+}
+
+/// Families whose arrows can keep a value of type `E`, captured by a
+/// function or as state. Every arrow family keeps `()`.
+pub trait Holds<'a, E>: CatFamily<'a> {
+    // arr (f env)
+    fn arrow_with<A, B, F>(env: E, f: F) -> Self::M<A, B>
+    where
+        F: Fn(&E, A) -> B + Copy + Send + Sync + 'a,
+        E: 'a,
+        A: 'a,
+        B: 'a;
+
+    /// An arrow holding `Some(init)`, which outputs what it holds and keeps
+    /// its input instead on every step.
+    fn register(init: E) -> Self::M<Option<E>, Option<E>>
+    where
+        E: 'a;
+}
+
+// An arrow of a function that captures nothing, which every family keeps.
+fn pure<'a, K, A, B, F>(f: F) -> K::M<A, B>
+where
+    K: ArrowFamily<'a> + ?Sized,
+    F: Fn(A) -> B + Copy + Send + Sync + 'a,
+    A: 'a,
+    B: 'a,
+{
+    K::arrow_with((), move |_, a| f(a))
+}
+
+fn call<A, B, F: Fn(A) -> B>(f: &F, a: A) -> B {
+    f(a)
+}
+
+pub trait ArrowFamily<'a>: CatFamily<'a> + Holds<'a, ()> {
+    // arr :: (a -> b) -> m a b
+    fn arrow<A, B, F>(f: F) -> Self::M<A, B>
+    where
+        F: Fn(A) -> B + 'a,
+        Self: Holds<'a, F>,
+        A: 'a,
+        B: 'a,
+    {
+        <Self as Holds<'a, F>>::arrow_with(f, call)
+    }
+
+    // first :: m a b -> m (a, c) (b, c)
+    fn fst<A, B, C>(f: Self::M<A, B>) -> Self::M<(A, C), (B, C)>
+    where
+        A: 'a,
+        B: 'a,
+        C: 'a;
+
+    // second :: m a b -> m (c,a) (c,b)
+    fn snd<A, B, C>(f: Self::M<A, B>) -> Self::M<(C, A), (C, B)>
+    where
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        Self::id().both(f)
+    }
+
+    // (***) :: m b c -> m b' c' -> m (b,b') (c,c')
+    // f *** g = first f >>> arr swap >>> first g >>> arr swap
+    fn both<A, B, A1, B1>(f: Self::M<A, B>, g: Self::M<A1, B1>) -> Self::M<(A, A1), (B, B1)>
+    where
+        A: 'a,
+        B: 'a,
+        A1: 'a,
+        B1: 'a,
+    {
+        let swapped = f.fst().then(pure::<Self, _, _, _>(swap));
+        swapped.then(g.fst()).then(pure::<Self, _, _, _>(swap))
+    }
+
+    // Also called fanout, because of it's relationship to |||/fanin/owise
+    // (&&&) :: m a b -> m a b' -> m a (b,b')
+    // f &&& g = arr (\a -> (a,a)) >>> f *** g
+    fn dup<A, B, B1>(f: Self::M<A, B>, g: Self::M<A, B1>) -> Self::M<A, (B, B1)>
+    where
+        A: Clone + 'a,
+        B: 'a,
+        B1: 'a,
+    {
+        pure::<Self, _, _, _>(|a: A| (a.clone(), a)).then(f.both(g))
+    }
+
+    // precomposition with a pure function
+    // (^>>) :: Arrow m => (b -> c) -> m c d -> m b d
+    fn after_pure<A0, A, B, F>(f: Self::M<A, B>, pre: F) -> Self::M<A0, B>
+    where
+        F: Fn(A0) -> A + 'a,
+        Self: Holds<'a, F>,
+        A0: 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::arrow(pre).then(f)
+    }
+
+    // postcomposition with a pure function
+    // (>>^) :: Arrow m => m b c -> (c -> d) -> m b d
+    fn then_pure<A, B, C, F>(f: Self::M<A, B>, post: F) -> Self::M<A, C>
+    where
+        F: Fn(B) -> C + 'a,
+        Self: Holds<'a, F>,
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        f.then(Self::arrow(post))
+    }
+}
+
+pub trait ChoiceFamily<'a>: ArrowFamily<'a> {
+    // left :: m b c -> m (Either b d) (Either c d)
+    fn left<A, B, D>(f: Self::M<A, B>) -> Self::M<Either<A, D>, Either<B, D>>
+    where
+        A: 'a,
+        B: 'a,
+        D: 'a;
+
+    // right :: a b c -> a (Either d b) (Either d c)
+    fn right<A, B, D>(f: Self::M<A, B>) -> Self::M<Either<D, A>, Either<D, B>>
+    where
+        A: 'a,
+        B: 'a,
+        D: 'a,
+    {
+        Self::id().split(f)
+    }
+
+    // (+++) :: m b c -> m b' c' -> m (Either b b') (Either c c')
+    fn split<A, B, A1, B1>(
+        f: Self::M<A, B>,
+        g: Self::M<A1, B1>,
+    ) -> Self::M<Either<A, A1>, Either<B, B1>>
+    where
+        A: 'a,
+        B: 'a,
+        A1: 'a,
+        B1: 'a,
+    {
+        let flipped = f.left().then(pure::<Self, _, _, _>(Either::flip));
+        flipped
+            .then(g.left())
+            .then(pure::<Self, _, _, _>(Either::flip))
+    }
+
+    // Also called fanin, because of it's relationship with &&&/fanout/dup
+    // (|||) :: m a b -> m c b -> m (Either a c) b
+    fn owise<A, B, C>(f: Self::M<A, B>, g: Self::M<C, B>) -> Self::M<Either<A, C>, B>
+    where
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        f.split(g).then(pure::<Self, _, _, _>(merge))
+    }
+}
+
+// There is no lazy knot-tying in rust, so the feedback is delayed by one
+// step and seeded with an initial value, as in CCA's loopD.
+pub trait LoopFamily<'a>: ArrowFamily<'a> {
+    /// Like `loop_d`, with the feedback kept by an arrow like the ones made
+    /// by `Holds::register`.
+    fn loop_through<A, B, D>(
+        f: Self::M<(A, D), (B, D)>,
+        register: Self::M<Option<D>, Option<D>>,
+    ) -> Self::M<A, B>
+    where
+        A: 'a,
+        B: 'a,
+        D: 'a;
+
+    // loopD :: d -> m (a, d) (b, d) -> m a b
+    fn loop_d<A, B, D>(f: Self::M<(A, D), (B, D)>, init: D) -> Self::M<A, B>
+    where
+        Self: Holds<'a, D>,
+        A: 'a,
+        B: 'a,
+        D: 'a,
+    {
+        Self::loop_through(f, Self::register(init))
+    }
+}
+
+/// Method syntax for `CatFamily`.
+pub trait Category<'a, A: 'a, B: 'a>: Morphism<'a, A, B> {
     // right-to-left composition
     // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
-    fn then<C>(
-        self,
-        consumer: <Self::CFamily as CatFamily>::M<B, C>,
-    ) -> <Self::CFamily as CatFamily>::M<A, C>
+    fn then<C>(self, consumer: ArrOf<'a, Self::Family, B, C>) -> ArrOf<'a, Self::Family, A, C>
     where
-        A: 'static,
-        B: 'static,
-        C: 'static;
+        C: 'a;
 
     // left-to-right composition
     // (.) or (<<<) :: Category cat => cat b c -> cat a b -> cat a c
-    fn after<A0>(
-        self,
-        producer: <Self::CFamily as CatFamily>::M<A0, A>,
-    ) -> <Self::CFamily as CatFamily>::M<A0, B>
+    fn after<A0>(self, producer: ArrOf<'a, Self::Family, A0, A>) -> ArrOf<'a, Self::Family, A0, B>
+    where
+        A0: 'a;
+}
+
+impl<'a, T, A: 'a, B: 'a> Category<'a, A, B> for T
+where
+    T: Morphism<'a, A, B>,
+{
+    fn then<C>(self, consumer: ArrOf<'a, T::Family, B, C>) -> ArrOf<'a, T::Family, A, C>
     where
-        Self: Sized,
-        A: 'static,
-        B: 'static,
-        A0: 'static,
+        C: 'a,
     {
-        Category::then(producer, self)
+        T::Family::then::<A, B, C>(self, consumer)
     }
-}
 
-pub trait ArrowFamily: CatFamily {
-    type M<T, U>: Arrow<T, U, AFamily = Self> + Category<T, U, CFamily = Self>;
+    fn after<A0>(self, producer: ArrOf<'a, T::Family, A0, A>) -> ArrOf<'a, T::Family, A0, B>
+    where
+        A0: 'a,
+    {
+        T::Family::then::<A0, A, B>(producer, self)
+    }
 }
 
-pub trait Arrow<A, B>: Category<A, B> {
-    type AFamily: ArrowFamily<M<A, B> = Self> + CatFamily<M<A, B> = Self>;
-
+/// Method syntax for `ArrowFamily`.
+pub trait Arrow<'a, A: 'a, B: 'a>:
+    Category<'a, A, B> + Morphism<'a, A, B, Family: ArrowFamily<'a>>
+{
     // arr :: (a -> b) -> m a b
     fn arrow<F>(f: F) -> Self
     where
-        F: Fn(A) -> B + Clone + 'static;
+        F: Fn(A) -> B + 'a,
+        Self::Family: Holds<'a, F>;
 
     // first :: m a b -> m (a, c) (b, c)
-    fn fst<C>(self) -> <Self::AFamily as ArrowFamily>::M<(A, C), (B, C)>
+    fn fst<C>(self) -> ArrOf<'a, Self::Family, (A, C), (B, C)>
     where
-        A: 'static,
-        B: 'static;
+        C: 'a;
 
     // second :: m a b -> m (c,a) (c,b)
-    fn snd<C>(self) -> <Self::AFamily as ArrowFamily>::M<(C, A), (C, B)>
+    fn snd<C>(self) -> ArrOf<'a, Self::Family, (C, A), (C, B)>
     where
-        Self: Sized,
-        A: 'static,
-        B: 'static,
-        C: 'static;
+        C: 'a;
 
     // (***) :: m b c -> m b' c' -> m (b,b') (c,c')
     fn both<A1, B1>(
         self,
-        g: <Self::AFamily as ArrowFamily>::M<A1, B1>,
-    ) -> <Self::AFamily as ArrowFamily>::M<(A, A1), (B, B1)>
+        g: ArrOf<'a, Self::Family, A1, B1>,
+    ) -> ArrOf<'a, Self::Family, (A, A1), (B, B1)>
     where
-        Self: Sized,
-        A: 'static,
-        B: 'static,
-        A1: 'static,
-        B1: 'static;
+        A1: 'a,
+        B1: 'a;
 
-    // Also called fanout, because of it's relationship to |||/fanin/owise
     // (&&&) :: m a b -> m a b' -> m a (b,b')
-    fn dup<B1>(
-        self,
-        g: <Self::AFamily as ArrowFamily>::M<A, B1>,
-    ) -> <Self::AFamily as ArrowFamily>::M<A, (B, B1)>
+    fn dup<B1>(self, g: ArrOf<'a, Self::Family, A, B1>) -> ArrOf<'a, Self::Family, A, (B, B1)>
     where
-        Self: Sized,
-        A: Clone + 'static,
-        B: 'static,
-        B1: 'static;
+        A: Clone,
+        B1: 'a;
 
-    // precomposition with a pure function
     // (^>>) :: Arrow m => (b -> c) -> m c d -> m b d
-    fn after_pure<F, A0>(self, f: F) -> <Self::AFamily as ArrowFamily>::M<A0, B>
+    fn after_pure<F, A0>(self, f: F) -> ArrOf<'a, Self::Family, A0, B>
     where
-        F: Fn(A0) -> A + Clone + 'static,
-        A: 'static,
-        B: 'static,
-        A0: 'static;
+        F: Fn(A0) -> A + 'a,
+        Self::Family: Holds<'a, F>,
+        A0: 'a;
 
-    // postcomposition with a pure function
     // (>>^) :: Arrow m => m b c -> (c -> d) -> m b d
-    fn then_pure<F, C>(self, f: F) -> <Self::AFamily as ArrowFamily>::M<A, C>
+    fn then_pure<F, C>(self, f: F) -> ArrOf<'a, Self::Family, A, C>
     where
-        F: Fn(B) -> C + Clone + 'static,
-        A: 'static,
-        B: 'static,
-        C: 'static;
+        F: Fn(B) -> C + 'a,
+        Self::Family: Holds<'a, F>,
+        C: 'a;
 }
 
-pub trait ChoiceFamily {
-    type M<T, U>: ArrowChoice<T, U, AFamily = Self> + Category<T, U, CFamily = Self>;
-}
+impl<'a, T, A: 'a, B: 'a> Arrow<'a, A, B> for T
+where
+    T: Morphism<'a, A, B>,
+    T::Family: ArrowFamily<'a>,
+{
+    fn arrow<F>(f: F) -> T
+    where
+        F: Fn(A) -> B + 'a,
+        T::Family: Holds<'a, F>,
+    {
+        T::Family::arrow::<A, B, F>(f)
+    }
 
-pub trait ArrowChoice<A, B>: Arrow<A, B> {
-    type AcFamily: ChoiceFamily<M<A, B> = Self>;
+    fn fst<C>(self) -> ArrOf<'a, T::Family, (A, C), (B, C)>
+    where
+        C: 'a,
+    {
+        T::Family::fst::<A, B, C>(self)
+    }
 
-    // left :: m b c -> m (Either b d) (Either c d)
-    fn left<D>(self) -> <Self::AcFamily as ChoiceFamily>::M<Either<A, D>, Either<B, D>>
+    fn snd<C>(self) -> ArrOf<'a, T::Family, (C, A), (C, B)>
+    where
+        C: 'a,
+    {
+        T::Family::snd::<A, B, C>(self)
+    }
+
+    fn both<A1, B1>(self, g: ArrOf<'a, T::Family, A1, B1>) -> ArrOf<'a, T::Family, (A, A1), (B, B1)>
     where
-        A: 'static,
-        B: 'static;
+        A1: 'a,
+        B1: 'a,
+    {
+        T::Family::both::<A, B, A1, B1>(self, g)
+    }
 
-    // right :: a b c -> a (Either d b) (Either d c)
-    fn right<D>(self) -> <Self::AcFamily as ChoiceFamily>::M<Either<D, A>, Either<D, B>>
+    fn dup<B1>(self, g: ArrOf<'a, T::Family, A, B1>) -> ArrOf<'a, T::Family, A, (B, B1)>
     where
-        A: 'static,
-        B: 'static,
-        D: 'static;
+        A: Clone,
+        B1: 'a,
+    {
+        T::Family::dup::<A, B, B1>(self, g)
+    }
+
+    fn after_pure<F, A0>(self, f: F) -> ArrOf<'a, T::Family, A0, B>
+    where
+        F: Fn(A0) -> A + 'a,
+        T::Family: Holds<'a, F>,
+        A0: 'a,
+    {
+        T::Family::after_pure::<A0, A, B, F>(self, f)
+    }
+
+    fn then_pure<F, C>(self, f: F) -> ArrOf<'a, T::Family, A, C>
+    where
+        F: Fn(B) -> C + 'a,
+        T::Family: Holds<'a, F>,
+        C: 'a,
+    {
+        T::Family::then_pure::<A, B, C, F>(self, f)
+    }
+}
+
+/// Method syntax for `ChoiceFamily`.
+pub trait ArrowChoice<'a, A: 'a, B: 'a>:
+    Arrow<'a, A, B> + Morphism<'a, A, B, Family: ChoiceFamily<'a>>
+{
+    // left :: m b c -> m (Either b d) (Either c d)
+    fn left<D>(self) -> ArrOf<'a, Self::Family, Either<A, D>, Either<B, D>>;
+
+    // right :: a b c -> a (Either d b) (Either d c)
+    fn right<D>(self) -> ArrOf<'a, Self::Family, Either<D, A>, Either<D, B>>;
 
     // (+++) :: m b c -> m b' c' -> m (Either b b') (Either c c')
     fn split<A1, B1>(
         self,
-        g: <Self::AcFamily as ChoiceFamily>::M<A1, B1>,
-    ) -> <Self::AcFamily as ChoiceFamily>::M<Either<A, A1>, Either<B, B1>>
+        g: ArrOf<'a, Self::Family, A1, B1>,
+    ) -> ArrOf<'a, Self::Family, Either<A, A1>, Either<B, B1>>
     where
-        A: 'static,
-        B: 'static,
-        A1: 'static,
-        B1: 'static;
+        A1: 'a,
+        B1: 'a;
 
-    // Also called fanin, because of it's relationship with &&&/fanout/dup
     // (|||) :: m a b -> m c b -> m (Either a c) b
-    fn owise<C>(
+    fn owise<C>(self, g: ArrOf<'a, Self::Family, C, B>) -> ArrOf<'a, Self::Family, Either<A, C>, B>
+    where
+        C: 'a;
+}
+
+impl<'a, T, A: 'a, B: 'a> ArrowChoice<'a, A, B> for T
+where
+    T: Morphism<'a, A, B>,
+    T::Family: ChoiceFamily<'a>,
+{
+    fn left<D>(self) -> ArrOf<'a, T::Family, Either<A, D>, Either<B, D>> {
+        T::Family::left::<A, B, D>(self)
+    }
+
+    fn right<D>(self) -> ArrOf<'a, T::Family, Either<D, A>, Either<D, B>> {
+        T::Family::right::<A, B, D>(self)
+    }
+
+    fn split<A1, B1>(
         self,
-        g: <Self::AcFamily as ChoiceFamily>::M<C, B>,
-    ) -> <Self::AcFamily as ChoiceFamily>::M<Either<A, C>, B>
+        g: ArrOf<'a, T::Family, A1, B1>,
+    ) -> ArrOf<'a, T::Family, Either<A, A1>, Either<B, B1>>
     where
-        A: 'static,
-        B: 'static,
-        C: 'static;
+        A1: 'a,
+        B1: 'a,
+    {
+        T::Family::split::<A, B, A1, B1>(self, g)
+    }
+
+    fn owise<C>(self, g: ArrOf<'a, T::Family, C, B>) -> ArrOf<'a, T::Family, Either<A, C>, B>
+    where
+        C: 'a,
+    {
+        T::Family::owise::<A, B, C>(self, g)
+    }
+}
+
+/// Method syntax for `LoopFamily`.
+pub trait ArrowLoop<'a, A: 'a, B: 'a, D: 'a>:
+    Arrow<'a, (A, D), (B, D)> + Morphism<'a, (A, D), (B, D), Family: LoopFamily<'a>>
+{
+    // loopD :: d -> m (a, d) (b, d) -> m a b
+    fn loop_d(self, init: D) -> ArrOf<'a, Self::Family, A, B>
+    where
+        Self::Family: Holds<'a, D>;
+}
+
+impl<'a, T, A: 'a, B: 'a, D: 'a> ArrowLoop<'a, A, B, D> for T
+where
+    T: Morphism<'a, (A, D), (B, D)>,
+    T::Family: LoopFamily<'a>,
+{
+    fn loop_d(self, init: D) -> ArrOf<'a, T::Family, A, B>
+    where
+        T::Family: Holds<'a, D>,
+    {
+        T::Family::loop_d::<A, B, D>(self, init)
+    }
+}
+
+// The combinators below are written only against the traits, so they work
+// for any arrow type with the right family.
+
+/// Sends inputs that satisfy `pred` through `on_true` and the rest through
+/// `on_false`.
+pub fn cond<'a, M, A, B, P>(pred: P, on_true: M, on_false: ArrOf<'a, M::Family, A, B>) -> M
+where
+    M: ArrowChoice<'a, A, B>,
+    P: Fn(&A) -> bool + 'a,
+    M::Family: Holds<'a, P>,
+    A: 'a,
+    B: 'a,
+{
+    let route =
+        <M::Family as Holds<'a, P>>::arrow_with(pred, |pred, a| Either::from((pred(&a), a)));
+    route.then(on_false.owise(on_true))
+}
+
+/// Runs `f` on `Left` values while carrying a context alongside, using the
+/// `fst` of the arrow returned by `left`.
+// first (left f)
+#[allow(clippy::type_complexity)]
+pub fn left_with<'a, M, A, B, D, C>(
+    f: M,
+) -> ArrOf<'a, M::Family, (Either<A, D>, C), (Either<B, D>, C)>
+where
+    M: ArrowChoice<'a, A, B>,
+    A: 'a,
+    B: 'a,
+    C: 'a,
+    D: 'a,
+{
+    f.left::<D>().fst::<C>()
 }
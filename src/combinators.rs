@@ -0,0 +1,376 @@
+//! Combinators from haskell's Control.Monad, written only against the traits
+//! in `monad.rs`.
+//!
+//! The family is inferred from the monadic values passed in, so callers
+//! rarely need to name it. Everything that walks a collection is built on
+//! `bind`, which gives `Vec` the usual list monad semantics (all
+//! combinations) rather than the zipping of its Applicative instance.
+
+use crate::monad::*;
+#[cfg(feature = "alloc")]
+use alloc::{rc::Rc, vec::Vec};
+
+type MOf<M, A, B> = Of<<M as Hkt<A>>::Kind, B>;
+
+fn ret<'a, M, A, B>(b: B) -> MOf<M, A, B>
+where
+    M: Monad<'a, A>,
+    B: 'a,
+{
+    Applicative::pure(b)
+}
+
+// The elements collected so far, newest first. The continuations of `bind`
+// may run more than once, so they can't take the accumulator; sharing the
+// tail makes pushing onto it O(1) instead of a copy.
+#[cfg(feature = "alloc")]
+#[derive(Clone)]
+struct Stack<A>(Option<Rc<(A, Stack<A>)>>);
+
+#[cfg(feature = "alloc")]
+impl<A: Clone> Stack<A> {
+    fn push(&self, a: A) -> Self {
+        Stack(Some(Rc::new((a, self.clone()))))
+    }
+
+    fn into_vec(self) -> Vec<A> {
+        let mut xs = Vec::new();
+        let mut node = self.0.as_deref();
+        while let Some((a, rest)) = node {
+            xs.push(a.clone());
+            node = rest.0.as_deref();
+        }
+        xs.reverse();
+        xs
+    }
+}
+
+// unlinks the nodes one at a time, as dropping them recursively would
+// overflow the stack on long lists
+#[cfg(feature = "alloc")]
+impl<A> Drop for Stack<A> {
+    fn drop(&mut self) {
+        let mut next = self.0.take();
+        while let Some(node) = next {
+            next = match Rc::try_unwrap(node) {
+                Ok((_, mut rest)) => rest.0.take(),
+                Err(_) => None,
+            };
+        }
+    }
+}
+
+// sequence :: [m a] -> m [a]
+#[cfg(feature = "alloc")]
+pub fn sequence<'a, M, A>(ms: impl IntoIterator<Item = M>) -> MOf<M, A, Vec<A>>
+where
+    M: Monad<'a, A> + Clone + 'a,
+    A: Clone + 'a,
+{
+    let stack = ms.into_iter().fold(ret::<M, A, _>(Stack(None)), |acc, m| {
+        acc.bind::<_, Stack<A>>(move |xs| {
+            m.clone()
+                .bind::<_, Stack<A>>(move |a| ret::<M, A, _>(xs.push(a)))
+        })
+    });
+    M::Kind::fmap(stack, Stack::into_vec)
+}
+
+// mapM :: (a -> m b) -> [a] -> m [b]
+#[cfg(feature = "alloc")]
+pub fn map_m<'a, T, F, M, A>(xs: impl IntoIterator<Item = T>, f: F) -> MOf<M, A, Vec<A>>
+where
+    F: Fn(T) -> M,
+    M: Monad<'a, A> + Clone + 'a,
+    A: Clone + 'a,
+{
+    sequence(xs.into_iter().map(f))
+}
+
+// mapM_ :: (a -> m b) -> [a] -> m ()
+pub fn for_each_m<'a, T, F, M, A>(xs: impl IntoIterator<Item = T>, f: F) -> MOf<M, A, ()>
+where
+    F: Fn(T) -> M,
+    M: Monad<'a, A> + Clone + 'a,
+    A: 'a,
+{
+    xs.into_iter().fold(ret::<M, A, _>(()), |acc, x| {
+        let m = f(x);
+        acc.bind::<_, ()>(move |()| m.clone().bind::<_, ()>(|_| ret::<M, A, _>(())))
+    })
+}
+
+/// `f` is shared by the binds, as a lazy monad keeps each of them until it
+/// is forced.
+// foldM :: (b -> a -> m b) -> b -> [a] -> m b
+#[cfg(feature = "alloc")]
+pub fn fold_m<'a, T, F, M, B>(init: B, xs: impl IntoIterator<Item = T>, f: F) -> MOf<M, B, B>
+where
+    F: Fn(B, T) -> M + 'a,
+    M: Monad<'a, B>,
+    T: Clone + 'a,
+    B: 'a,
+{
+    let f = Rc::new(f);
+    xs.into_iter().fold(ret::<M, B, _>(init), |acc, x| {
+        let f = f.clone();
+        acc.bind::<_, B>(move |b| f(b, x.clone()))
+    })
+}
+
+// filterM :: (a -> m Bool) -> [a] -> m [a]
+#[cfg(feature = "alloc")]
+pub fn filter_m<'a, T, P, M>(xs: impl IntoIterator<Item = T>, p: P) -> MOf<M, bool, Vec<T>>
+where
+    P: Fn(&T) -> M + 'a,
+    M: Monad<'a, bool>,
+    T: Clone + 'a,
+{
+    let p = Rc::new(p);
+    let stack = xs
+        .into_iter()
+        .fold(ret::<M, bool, _>(Stack(None)), |acc, x| {
+            let p = p.clone();
+            acc.bind::<_, Stack<T>>(move |kept| {
+                let x = x.clone();
+                p(&x).bind::<_, Stack<T>>(move |keep| match keep {
+                    true => ret::<M, bool, _>(kept.push(x.clone())),
+                    false => ret::<M, bool, _>(kept.clone()),
+                })
+            })
+        });
+    M::Kind::fmap(stack, Stack::into_vec)
+}
+
+// replicateM :: Int -> m a -> m [a]
+#[cfg(feature = "alloc")]
+pub fn replicate_m<'a, M, A>(n: usize, m: M) -> MOf<M, A, Vec<A>>
+where
+    M: Monad<'a, A> + Clone + 'a,
+    A: Clone + 'a,
+{
+    sequence(core::iter::repeat_n(m, n))
+}
+
+// zipWithM :: (a -> b -> m c) -> [a] -> [b] -> m [c]
+#[cfg(feature = "alloc")]
+pub fn zip_with_m<'a, T, U, F, M, A>(
+    xs: impl IntoIterator<Item = T>,
+    ys: impl IntoIterator<Item = U>,
+    f: F,
+) -> MOf<M, A, Vec<A>>
+where
+    F: Fn(T, U) -> M,
+    M: Monad<'a, A> + Clone + 'a,
+    A: Clone + 'a,
+{
+    sequence(xs.into_iter().zip(ys).map(|(x, y)| f(x, y)))
+}
+
+/// Like `map_m` over borrowed elements. Only `lift_a2` is used, so no clones
+/// are needed and a `Validation` collects every failure.
+// traverse :: (a -> f b) -> t a -> f (t b)
+#[cfg(feature = "alloc")]
+pub fn traverse_ref<'a, 'r, T, F, M, A>(
+    xs: impl IntoIterator<Item = &'r T>,
+    f: F,
+) -> MOf<M, A, Vec<A>>
+where
+    F: Fn(&'r T) -> M,
+    M: Applicative<'a, A>,
+    A: 'a,
+    T: 'r,
+{
+    xs.into_iter().fold(M::Kind::pure(Vec::new()), |acc, x| {
+        M::Kind::lift_a2(acc, f(x), |mut xs: Vec<A>, a| {
+            xs.push(a);
+            xs
+        })
+    })
+}
+
+// when :: Applicative f => Bool -> f () -> f ()
+pub fn when<'a, M>(cond: bool, act: M) -> M
+where
+    M: Applicative<'a, ()>,
+{
+    if cond {
+        act
+    } else {
+        M::pure(())
+    }
+}
+
+// unless :: Applicative f => Bool -> f () -> f ()
+pub fn unless<'a, M>(cond: bool, act: M) -> M
+where
+    M: Applicative<'a, ()>,
+{
+    when(!cond, act)
+}
+
+// guard :: MonadPlus m => Bool -> m ()
+pub fn guard<'a, M>(cond: bool) -> M
+where
+    M: MonadPlus<'a, ()>,
+{
+    if cond {
+        M::pure(())
+    } else {
+        M::mzero()
+    }
+}
+
+/// Repeats `m` forever, so it only returns if `m` short-circuits, as `None`,
+/// `Left` or an empty `Vec` do. Any other value loops without end, like
+/// haskell's `forever`.
+// forever :: m a -> m b
+pub fn forever<'a, M, A, B>(m: M) -> MOf<M, A, B>
+where
+    M: Monad<'a, A> + Foldable<A>,
+    A: 'a,
+    B: 'a,
+{
+    // every repetition binds the same value, so it short-circuits on the
+    // first or never does, and one with no elements to bind is the former
+    loop {
+        if m.length() == 0 {
+            return m.bind::<_, B>(|_| unreachable!("bound an empty value"));
+        }
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::impls::{either::Either, lazy::Lazy};
+
+    fn half(n: usize) -> Option<usize> {
+        n.is_multiple_of(2).then_some(n / 2)
+    }
+
+    fn checked_half(n: usize) -> Either<String, usize> {
+        match half(n) {
+            Some(n) => Either::Right(n),
+            None => Either::Left(format!("{} is odd", n)),
+        }
+    }
+
+    #[test]
+    fn test_option() {
+        assert_eq!(sequence(vec![Some(1), Some(2)]), Some(vec![1, 2]));
+        assert_eq!(sequence(vec![Some(1), None]), None);
+
+        assert_eq!(map_m(vec![2, 4, 6], half), Some(vec![1, 2, 3]));
+        assert_eq!(map_m(vec![2, 3], half), None);
+        assert_eq!(for_each_m(vec![2, 4], half), Some(()));
+        assert_eq!(for_each_m(vec![2, 5], half), None);
+
+        let halve_all = |acc: usize, _| half(acc);
+        assert_eq!(fold_m(8, 0..3, halve_all), Some(1));
+        assert_eq!(fold_m(8, 0..4, halve_all), None);
+
+        let small = |n: &usize| (*n < 10).then_some(n.is_multiple_of(2));
+        assert_eq!(filter_m(vec![1, 2, 3, 4], small), Some(vec![2, 4]));
+        assert_eq!(filter_m(vec![1, 20], small), None);
+
+        assert_eq!(replicate_m(3, Some('a')), Some(vec!['a'; 3]));
+        assert_eq!(
+            zip_with_m(vec![4, 8], vec![1, 2], |a, b| half(a + b + b)),
+            Some(vec![3, 6])
+        );
+
+        let words = vec!["1".to_string(), "22".to_string()];
+        let parse = |s: &String| s.parse::<usize>().ok();
+        assert_eq!(traverse_ref(&words, parse), Some(vec![1, 22]));
+        assert_eq!(traverse_ref(&["x".to_string()], parse), None);
+        // the input is only borrowed
+        assert_eq!(words.len(), 2);
+
+        assert_eq!(guard::<Option<()>>(true), Some(()));
+        assert_eq!(guard::<Option<()>>(false), None);
+        assert_eq!(when(false, None), Some(()));
+        assert_eq!(when(true, None), None);
+        assert_eq!(unless(true, None), Some(()));
+
+        let stop: Option<()> = None;
+        assert_eq!(forever::<_, _, usize>(stop), None);
+    }
+
+    #[test]
+    fn test_long() {
+        // the accumulator is not copied for each element
+        let n = 100_000;
+        let res = sequence((0..n).map(Some)).unwrap();
+        assert_eq!(res.len(), n);
+        assert_eq!(res[n - 1], n - 1);
+        let res = filter_m(0..n, |x| Some(x % 3 == 0)).unwrap();
+        assert_eq!(res.len(), n.div_ceil(3));
+        let res = replicate_m(n, Either::<(), _>::Right(1));
+        assert_eq!(res.fmap(|xs| xs.len()), Either::Right(n));
+    }
+
+    #[test]
+    fn test_vec() {
+        // every combination of choices
+        let res = sequence(vec![vec![1, 2], vec![3, 4]]);
+        assert_eq!(res, vec![vec![1, 3], vec![1, 4], vec![2, 3], vec![2, 4]]);
+
+        let res = map_m(vec![1, 2], |n| vec![n, n * 10]);
+        assert_eq!(
+            res,
+            vec![vec![1, 2], vec![1, 20], vec![10, 2], vec![10, 20]]
+        );
+
+        // powerset
+        let res = filter_m(vec![1, 2, 3], |_| vec![true, false]);
+        assert_eq!(res.len(), 8);
+        assert_eq!(res[0], vec![1, 2, 3]);
+        assert_eq!(res[7], Vec::<usize>::new());
+
+        let res = fold_m(0, vec![1, 2], |acc, n| vec![acc + n, acc * n]);
+        assert_eq!(res, vec![3, 2, 2, 0]);
+
+        assert_eq!(replicate_m(2, vec![0, 1]).len(), 4);
+        assert_eq!(for_each_m(vec![1, 2], |n| vec![(); n]), vec![(); 2]);
+        assert_eq!(
+            zip_with_m(vec![1, 2], vec![3, 4, 5], |a, b| vec![a * b]),
+            vec![vec![3, 8]]
+        );
+
+        assert_eq!(when(false, Vec::new()), vec![()]);
+        assert_eq!(forever::<_, (), ()>(Vec::new()), Vec::new());
+    }
+
+    #[test]
+    fn test_either() {
+        assert_eq!(map_m(vec![2, 4], checked_half), Either::Right(vec![1, 2]));
+        // the first error short-circuits
+        assert_eq!(
+            map_m(vec![2, 3, 5], checked_half),
+            Either::Left("3 is odd".to_string())
+        );
+        assert_eq!(
+            fold_m(12, 0..3, |acc, _| checked_half(acc)),
+            Either::Left("3 is odd".to_string())
+        );
+        assert_eq!(
+            zip_with_m(vec![1], vec![1], |a, b| checked_half(a + b)),
+            Either::Right(vec![1])
+        );
+        assert_eq!(
+            unless(false, Either::Left("failed")),
+            Either::Left("failed")
+        );
+        let stop: Either<&str, ()> = Either::Left("stopped");
+        assert_eq!(forever::<_, _, usize>(stop), Either::Left("stopped"));
+    }
+
+    #[test]
+    fn test_lazy() {
+        // nothing runs until the result is forced
+        let sum = fold_m(0, 1..=3, |acc, n| Lazy::new(move || acc + n));
+        assert!(!sum.is_forced());
+        assert_eq!(sum.into_value(), 6);
+    }
+}
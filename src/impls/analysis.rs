@@ -0,0 +1,197 @@
+use crate::{impls::either::Either, monad::*};
+use core::marker::PhantomData;
+
+/// Static analysis of a selective computation that records every effect it
+/// might perform, including effects that `select` would skip at runtime.
+///
+/// Like haskell's `Const`, an `Over` never holds a value of type `A`, only
+/// the effects accumulated in `M`.
+pub struct Over<M, A>(pub M, PhantomData<A>);
+
+/// Static analysis of a selective computation that records only the effects
+/// it is guaranteed to perform, skipping everything behind a `select`.
+pub struct Under<M, A>(pub M, PhantomData<A>);
+
+impl<M, A> Over<M, A> {
+    pub fn new(effects: M) -> Self {
+        Self(effects, PhantomData)
+    }
+
+    pub fn effects(self) -> M {
+        self.0
+    }
+}
+
+impl<M, A> Under<M, A> {
+    pub fn new(effects: M) -> Self {
+        Self(effects, PhantomData)
+    }
+
+    pub fn effects(self) -> M {
+        self.0
+    }
+}
+
+pub struct OverFamily<M>(PhantomData<M>);
+
+pub struct UnderFamily<M>(PhantomData<M>);
+
+impl<M> Kind for OverFamily<M> {
+    type M<T> = Over<M, T>;
+}
+
+impl<M, A> Hkt<A> for Over<M, A> {
+    type Kind = OverFamily<M>;
+}
+
+impl<'a, M> FunctorFamily<'a> for OverFamily<M> {
+    fn fmap<A, B, F>(over: Over<M, A>, _: F) -> Over<M, B>
+    where
+        F: Fn(A) -> B,
+    {
+        Over::new(over.0)
+    }
+}
+
+// there are no values to borrow, so the effects are cloned
+impl<'a, M: Clone> FunctorRefFamily<'a> for OverFamily<M> {
+    fn fmap_ref<'r, A, B, F>(over: &'r Over<M, A>, _: F) -> Over<M, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        Over::new(over.0.clone())
+    }
+}
+
+impl<'a, M> FunctorMutFamily<'a> for OverFamily<M> {
+    fn fmap_in_place<A, F>(_: &mut Over<M, A>, _: F)
+    where
+        F: FnMut(&mut A),
+    {
+    }
+}
+
+impl<'a, M: Monoid> ApplicativeFamily<'a> for OverFamily<M> {
+    fn pure<A>(_: A) -> Over<M, A> {
+        Over::new(M::empty())
+    }
+
+    fn lift_a2<A, B, C, F>(a: Over<M, A>, b: Over<M, B>, _: F) -> Over<M, C>
+    where
+        F: Fn(A, B) -> C,
+    {
+        Over::new(a.0.combine(b.0))
+    }
+}
+
+impl<'a, M: Monoid> SelectiveFamily<'a> for OverFamily<M> {
+    // select = selectA
+    fn select<A, B, F>(x: Over<M, Either<A, B>>, f: Over<M, F>) -> Over<M, B>
+    where
+        F: Fn(A) -> B,
+    {
+        Self::select_a(x, f)
+    }
+}
+
+impl<M> Kind for UnderFamily<M> {
+    type M<T> = Under<M, T>;
+}
+
+impl<M, A> Hkt<A> for Under<M, A> {
+    type Kind = UnderFamily<M>;
+}
+
+impl<'a, M> FunctorFamily<'a> for UnderFamily<M> {
+    fn fmap<A, B, F>(under: Under<M, A>, _: F) -> Under<M, B>
+    where
+        F: Fn(A) -> B,
+    {
+        Under::new(under.0)
+    }
+}
+
+// there are no values to borrow, so the effects are cloned
+impl<'a, M: Clone> FunctorRefFamily<'a> for UnderFamily<M> {
+    fn fmap_ref<'r, A, B, F>(under: &'r Under<M, A>, _: F) -> Under<M, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        Under::new(under.0.clone())
+    }
+}
+
+impl<'a, M> FunctorMutFamily<'a> for UnderFamily<M> {
+    fn fmap_in_place<A, F>(_: &mut Under<M, A>, _: F)
+    where
+        F: FnMut(&mut A),
+    {
+    }
+}
+
+impl<'a, M: Monoid> ApplicativeFamily<'a> for UnderFamily<M> {
+    fn pure<A>(_: A) -> Under<M, A> {
+        Under::new(M::empty())
+    }
+
+    fn lift_a2<A, B, C, F>(a: Under<M, A>, b: Under<M, B>, _: F) -> Under<M, C>
+    where
+        F: Fn(A, B) -> C,
+    {
+        Under::new(a.0.combine(b.0))
+    }
+}
+
+impl<'a, M: Monoid> SelectiveFamily<'a> for UnderFamily<M> {
+    // select (Under x) _ = Under x
+    fn select<A, B, F>(x: Under<M, Either<A, B>>, _: Under<M, F>) -> Under<M, B>
+    where
+        F: Fn(A) -> B,
+    {
+        Under::new(x.0)
+    }
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_over_under() {
+        let effect = |name: &'static str| vec![name];
+
+        let over = OverFamily::if_s(
+            Over::new(effect("read flag")),
+            Over::<_, usize>::new(effect("launch")),
+            Over::new(effect("abort")),
+        );
+        let mut effects = over.effects();
+        effects.sort_unstable();
+        assert_eq!(effects, vec!["abort", "launch", "read flag"]);
+
+        let under = UnderFamily::if_s(
+            Under::new(effect("read flag")),
+            Under::<_, usize>::new(effect("launch")),
+            Under::new(effect("abort")),
+        );
+        assert_eq!(under.effects(), vec!["read flag"]);
+
+        // applicative effects are unconditional, so both analyses agree
+        let over =
+            Over::<_, usize>::new(effect("a")).lift_a2(Over::new(effect("b")), |a, b: usize| a + b);
+        let under = Under::<_, usize>::new(effect("a"))
+            .lift_a2(Under::new(effect("b")), |a, b: usize| a + b);
+        assert_eq!(over.effects(), under.effects());
+
+        let when = UnderFamily::when_s(Under::new(effect("check")), Under::new(effect("act")));
+        assert_eq!(when.effects(), vec!["check"]);
+
+        // mapping never touches the effects
+        let mut over = Over::<_, usize>::new(effect("a"));
+        over.fmap_in_place(|n| *n += 1);
+        assert_eq!(over.fmap_ref(|n| n + 1).effects(), vec!["a"]);
+        assert_eq!(over.effects(), vec!["a"]);
+    }
+}
@@ -0,0 +1,281 @@
+//! Causal commutative arrows, reified so they can be normalized.
+//!
+//! A term built from `arr`, `then`, `fst`, `loop_d` and `init` can always be
+//! rewritten into a single pure function threading one state value, which
+//! runs as `arrow(f).loop_d(state)` (Liu, Cheng and Hudak, "Causal
+//! Commutative Arrows Revisited"). Here the rewriting is done by the types:
+//! each term knows the shape of its state, and `normalize` composes the pure
+//! functions of its parts, so nothing is interpreted while stepping.
+//!
+//! `eval` builds the same term directly out of `Circuit` combinators, which
+//! is mostly useful for checking the normal form against.
+
+use crate::{
+    arrow::*,
+    impls::circuit::{arrow, Circuit},
+};
+
+/// A causal arrow term from `A`, whose state and outputs live for `'a`.
+pub trait Cca<'a, A: 'a> {
+    type Output: 'a;
+    type State: 'a;
+
+    /// The initial state and the pure step function of the normal form.
+    #[allow(clippy::type_complexity)]
+    fn normalize(
+        self,
+    ) -> Normal<Self::State, impl Fn(A, Self::State) -> (Self::Output, Self::State) + 'a>;
+
+    /// Runs the term by composing a circuit for each constructor.
+    fn eval(self) -> Circuit<'a, A, Self::Output>;
+
+    /// Runs the normal form, as a single `loop_d` around a pure function.
+    fn normalized(self) -> Circuit<'a, A, Self::Output>
+    where
+        Self: Sized,
+    {
+        let Normal { state, step } = self.normalize();
+        arrow(move |(a, s)| step(a, s)).loop_d(state)
+    }
+
+    // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
+    fn then<G>(self, g: G) -> Then<Self, G>
+    where
+        G: Cca<'a, Self::Output>,
+        Self: Sized,
+    {
+        Then(self, g)
+    }
+
+    // first :: m a b -> m (a, c) (b, c)
+    fn fst(self) -> First<Self>
+    where
+        Self: Sized,
+    {
+        First(self)
+    }
+}
+
+/// Feedback through a delay, with the term's own `D` output fed back into
+/// the next step.
+pub trait CcaLoop<'a, A: 'a, D: 'a>: Cca<'a, (A, D)> {
+    // loopD :: d -> m (a, d) (b, d) -> m a b
+    fn loop_d(self, init: D) -> Loop<Self, D>
+    where
+        Self: Sized,
+    {
+        Loop(self, init)
+    }
+}
+
+impl<'a, T, A: 'a, D: 'a> CcaLoop<'a, A, D> for T where T: Cca<'a, (A, D)> {}
+
+/// A normal form: the state of every `loop_d` and `init` in a term, and a
+/// pure function that steps all of them at once.
+pub struct Normal<S, F> {
+    pub state: S,
+    pub step: F,
+}
+
+// arr :: (a -> b) -> m a b
+pub fn arr<F>(f: F) -> Arr<F> {
+    Arr(f)
+}
+
+/// A delay that outputs `i` first, then each input one step late.
+// init :: b -> m b b
+pub fn init<A>(i: A) -> Init<A> {
+    Init(i)
+}
+
+#[derive(Clone)]
+pub struct Arr<F>(F);
+
+#[derive(Clone)]
+pub struct Then<F, G>(F, G);
+
+#[derive(Clone)]
+pub struct First<F>(F);
+
+#[derive(Clone)]
+pub struct Loop<F, D>(F, D);
+
+#[derive(Clone)]
+pub struct Init<A>(A);
+
+impl<'a, F, A: 'a, B: 'a> Cca<'a, A> for Arr<F>
+where
+    F: Fn(A) -> B + 'a,
+{
+    type Output = B;
+    type State = ();
+
+    fn normalize(self) -> Normal<(), impl Fn(A, ()) -> (B, ()) + 'a> {
+        let f = self.0;
+        Normal {
+            state: (),
+            step: move |a, ()| (f(a), ()),
+        }
+    }
+
+    fn eval(self) -> Circuit<'a, A, B> {
+        arrow(self.0)
+    }
+}
+
+impl<'a, F, G, A: 'a> Cca<'a, A> for Then<F, G>
+where
+    F: Cca<'a, A>,
+    G: Cca<'a, F::Output>,
+{
+    type Output = G::Output;
+    type State = (F::State, G::State);
+
+    fn normalize(
+        self,
+    ) -> Normal<Self::State, impl Fn(A, Self::State) -> (G::Output, Self::State) + 'a> {
+        let (f, g) = (self.0.normalize(), self.1.normalize());
+        let (f_step, g_step) = (f.step, g.step);
+        let step = move |a, (sf, sg)| {
+            let (b, sf) = f_step(a, sf);
+            let (c, sg) = g_step(b, sg);
+            (c, (sf, sg))
+        };
+        Normal {
+            state: (f.state, g.state),
+            step,
+        }
+    }
+
+    fn eval(self) -> Circuit<'a, A, G::Output> {
+        self.0.eval().then(self.1.eval())
+    }
+}
+
+impl<'a, F, A: 'a, C: 'a> Cca<'a, (A, C)> for First<F>
+where
+    F: Cca<'a, A>,
+{
+    type Output = (F::Output, C);
+    type State = F::State;
+
+    fn normalize(
+        self,
+    ) -> Normal<F::State, impl Fn((A, C), F::State) -> (Self::Output, F::State) + 'a> {
+        let Normal { state, step: f } = self.0.normalize();
+        let step = move |(a, c), s| {
+            let (b, s) = f(a, s);
+            ((b, c), s)
+        };
+        Normal { state, step }
+    }
+
+    fn eval(self) -> Circuit<'a, (A, C), (F::Output, C)> {
+        self.0.eval().fst()
+    }
+}
+
+impl<'a, F, A: 'a, B: 'a, D: 'a> Cca<'a, A> for Loop<F, D>
+where
+    F: Cca<'a, (A, D), Output = (B, D)>,
+{
+    type Output = B;
+    type State = (D, F::State);
+
+    fn normalize(self) -> Normal<Self::State, impl Fn(A, Self::State) -> (B, Self::State) + 'a> {
+        let Normal { state, step: f } = self.0.normalize();
+        let step = move |a, (d, s)| {
+            let ((b, d), s) = f((a, d), s);
+            (b, (d, s))
+        };
+        Normal {
+            state: (self.1, state),
+            step,
+        }
+    }
+
+    fn eval(self) -> Circuit<'a, A, B> {
+        self.0.eval().loop_d(self.1)
+    }
+}
+
+impl<'a, A: 'a> Cca<'a, A> for Init<A> {
+    type Output = A;
+    type State = A;
+
+    fn normalize(self) -> Normal<A, impl Fn(A, A) -> (A, A) + 'a> {
+        Normal {
+            state: self.0,
+            step: |a, prev| (prev, a),
+        }
+    }
+
+    fn eval(self) -> Circuit<'a, A, A> {
+        Circuit::accum(self.0, |a, prev| (prev, a))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn check<T>(term: T)
+    where
+        T: Cca<'static, i64, Output = i64> + Clone,
+    {
+        for seed in 0..20 {
+            let mut rng = StdRng::seed_from_u64(seed);
+            let len = rng.gen_range(0..50);
+            let xs: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let expected: Vec<_> = term.clone().eval().run(xs.clone()).collect();
+            let res: Vec<_> = term.clone().normalized().run(xs.clone()).collect();
+            assert_eq!(res, expected, "seed {seed}, input {xs:?}");
+        }
+    }
+
+    // y[n] = x[n] - x[n - 1]
+    fn diff() -> impl Cca<'static, i64, Output = i64> + Clone {
+        arr(|x| (x, x))
+            .then(init(0).fst())
+            .then(arr(|(prev, x): (i64, i64)| x - prev))
+    }
+
+    // y[n] = x[n] + y[n - 1]
+    fn sum() -> impl Cca<'static, i64, Output = i64> + Clone {
+        arr(|(x, total): (i64, i64)| (x + total, x + total)).loop_d(0)
+    }
+
+    #[test]
+    fn test_normalize() {
+        check(arr(|x: i64| x * 3));
+        check(init(7));
+        check(diff());
+        check(sum());
+        // the sum of differences gives back the input
+        check(sum().then(diff()));
+        check(diff().then(sum()).then(init(1)));
+    }
+
+    #[test]
+    fn test_nested_loops() {
+        // the feedback passes through a delay and a second loop
+        let body = arr(|(x, fb): (i64, i64)| x + fb)
+            .then(sum())
+            .then(init(3))
+            .then(arr(|y: i64| (y, y % 7)));
+        check(body.loop_d(0));
+    }
+
+    #[test]
+    fn test_normal_form() {
+        let term = init(0).then(arr(|(x, t): (i64, i64)| (x + t, x + t)).loop_d(10));
+        let Normal { state, step } = term.normalize();
+        // one flat state: the delay of `init`, then the feedback of `loop_d`
+        assert_eq!(state, (0, (10, ())));
+        let (y, state) = step(5, state);
+        assert_eq!((y, state), (10, (5, (10, ()))));
+        let (y, _) = step(1, state);
+        assert_eq!(y, 15);
+    }
+}
@@ -1,283 +1,135 @@
-use crate::{arrow::*, impls::either::Either};
+use crate::{
+    arrow::*,
+    impls::stages::{circuit_impls, Repr},
+};
+use alloc::{boxed::Box, vec::Vec};
 
 pub struct CircuitFamily;
 
-impl CatFamily for CircuitFamily {
-    type M<A, B> = Circuit<A, B>;
+type Step<'a, A, B> = Box<dyn FnMut(A) -> B + 'a>;
 
-    fn id<A>() -> Self::M<A, A> {
-        let id = |a| (Self::id(), a);
-        Circuit::new(id)
-    }
-}
+// the stages of a `Circuit` all run on the thread that steps it
+type Link<'a> = core::convert::Infallible;
 
-pub struct Circuit<A, B> {
-    step: Box<dyn FnOnce(A) -> (Circuit<A, B>, B)>,
+pub struct Circuit<'a, A, B> {
+    repr: Repr<Step<'a, A, B>, Step<'a, bool, ()>, Link<'a>, A, B>,
 }
 
-impl<A, B> Category<A, B> for Circuit<A, B> {
-    type CFamily = CircuitFamily;
-
-    // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
-    fn then<C>(self, consumer: Circuit<B, C>) -> Circuit<A, C>
-    where
-        A: 'static,
-        B: 'static,
-        C: 'static,
-    {
-        let inner = |a| {
-            let (producer, b) = self.call(a);
-            let (consumer, c) = consumer.call(b);
-            (producer.then(consumer), c)
-        };
-        Circuit::new(inner)
-    }
+circuit_impls!(Circuit, CircuitFamily);
 
-    // (.) or (<<<) :: Category cat => cat b c -> cat a b -> cat a c
-    fn after<A0>(self, producer: Circuit<A0, A>) -> Circuit<A0, B>
-    where
-        A: 'static,
-        B: 'static,
-        A0: 'static,
-    {
-        producer.then(self)
+impl<'a, F, A: 'a, B: 'a> From<F> for Circuit<'a, A, B>
+where
+    F: FnOnce(A) -> B + Clone + 'a,
+{
+    fn from(f: F) -> Self {
+        Circuit::from_step(move |a| f.clone()(a))
     }
 }
 
-impl ArrowFamily for CircuitFamily {
-    type M<A, B> = Circuit<A, B>;
-}
-
-fn flip<A, B>((a, b): (A, B)) -> (B, A) {
-    (b, a)
+pub fn arrow<'a, F, A, B>(f: F) -> Circuit<'a, A, B>
+where
+    F: Fn(A) -> B + 'a,
+    A: 'a,
+    B: 'a,
+{
+    CircuitFamily::arrow(f)
 }
 
-impl<A, B> Arrow<A, B> for Circuit<A, B> {
-    type AFamily = CircuitFamily;
-
-    fn arrow<F>(f: F) -> Self
-    where
-        F: Fn(A) -> B + Clone + 'static,
-    {
-        f.into()
-    }
-
-    // first :: m a b -> m (a, c) (b, c)
-    fn fst<C>(self) -> Circuit<(A, C), (B, C)>
-    where
-        A: 'static,
-        B: 'static,
-    {
-        let f = |(a, c)| {
-            let (circ_next, b) = self.call(a);
-            (circ_next.fst(), (b, c))
-        };
-        Circuit::new(f)
-    }
-
-    // second :: m a b -> m (c,a) (c,b)
-    fn snd<C>(self) -> <Self::AFamily as ArrowFamily>::M<(C, A), (C, B)>
-    where
-        Self: Sized,
-        A: 'static,
-        B: 'static,
-        C: 'static,
-    {
-        arrow(std::convert::identity).both(self)
-    }
-
-    // (***) :: a b c -> a b' c' -> a (b,b') (c,c')
-    // f *** g = first f >>> arr swap >>> first g >>> arr swap   where swap ~(x,y) = (y,x)
-    fn both<A1, B1>(self, right_arrow: Circuit<A1, B1>) -> Circuit<(A, A1), (B, B1)>
-    where
-        A: 'static,
-        B: 'static,
-        A1: 'static,
-        B1: 'static,
-    {
-        self.fst()
-            .then_pure(flip)
-            .then(right_arrow.fst())
-            .then_pure(flip)
-    }
-
-    // (&&&) :: m a b -> m a b' -> m a (b,b')
-    // f &&& g = arr (\a -> (a,a)) >>> f *** g
-    fn dup<B1>(self, right_arrow: Circuit<A, B1>) -> Circuit<A, (B, B1)>
-    where
-        Self: Sized,
-        A: Clone + 'static,
-        B: 'static,
-        B1: 'static,
-    {
-        self.both(right_arrow).after_pure(|a: A| (a.clone(), a))
+impl<'a, A: 'a> Circuit<'a, A, A> {
+    #[cfg(test)]
+    fn id() -> Self {
+        CircuitFamily::id()
     }
+}
 
-    // precomposition with a pure function
-    // (^>>) :: Arrow m => (b -> c) -> m c d -> m b d
-    fn after_pure<F, A0>(self, f: F) -> Circuit<A0, B>
+impl<'a, A, B> Circuit<'a, A, B> {
+    /// Feeds every input through the circuit in turn, lazily.
+    pub fn run<I>(self, xs: I) -> Run<'a, A, B, I::IntoIter>
     where
-        F: Fn(A0) -> A + Clone + 'static,
-        A: 'static,
-        B: 'static,
-        A0: 'static,
+        I: IntoIterator<Item = A>,
     {
-        arrow(f).then(self)
+        Run {
+            circuit: self,
+            inputs: xs.into_iter(),
+        }
     }
+}
 
-    // postcomposition with a pure function
-    // (>>^) :: Arrow m => m b c -> (c -> d) -> m b d
-    fn then_pure<F, C>(self, f: F) -> Circuit<A, C>
+impl<'a, A: 'a, B: 'a> Circuit<'a, A, B> {
+    /// A circuit that ignores its input and produces the outputs generated
+    /// from `seed`, one per step.
+    pub fn unfold<S, F>(seed: S, mut f: F) -> Self
     where
-        F: Fn(B) -> C + Clone + 'static,
-        A: 'static,
-        B: 'static,
-        C: 'static,
+        S: 'a,
+        F: FnMut(S) -> (B, S) + 'a,
     {
-        self.then(arrow(f))
+        Self::accum(seed, move |_, s| f(s))
     }
 }
 
-impl ChoiceFamily for CircuitFamily {
-    type M<A, B> = Circuit<A, B>;
+/// The iterator returned by `Circuit::run` and `IteratorExt::through`.
+pub struct Run<'a, A, B, I> {
+    circuit: Circuit<'a, A, B>,
+    inputs: I,
 }
 
-impl<A, B> ArrowChoice<A, B> for Circuit<A, B> {
-    type AcFamily = CircuitFamily;
-
-    // left :: a b c -> a (Either b d) (Either c d)
-    fn left<D>(self) -> Circuit<Either<A, D>, Either<B, D>>
-    where
-        A: 'static,
-        B: 'static,
-    {
-        let f = move |either_ad| match either_ad {
-            Either::Left(a) => {
-                let (circ_new, res) = self.call(a);
-                (circ_new.left(), Either::Left(res))
-            }
-            Either::Right(d) => (self.left(), Either::Right(d)),
-        };
-        Circuit::new(f)
-    }
-
-    // right :: a b c -> a (Either d b) (Either d c)
-    fn right<D>(self) -> Circuit<Either<D, A>, Either<D, B>>
-    where
-        A: 'static,
-        B: 'static,
-        D: 'static,
-    {
-        arrow(std::convert::identity).split(self)
-    }
-
-    // (+++) :: a b c -> a b' c' -> a (Either b b') (Either c c')
-    fn split<A1, B1>(self, g: Circuit<A1, B1>) -> Circuit<Either<A, A1>, Either<B, B1>>
-    where
-        A: 'static,
-        B: 'static,
-        A1: 'static,
-        B1: 'static,
-    {
-        self.left()
-            .then_pure(Either::flip)
-            .then(g.left())
-            .then_pure(Either::flip)
-    }
-
-    // (|||) :: m a b -> m c b -> m (Either a c) b
-    fn owise<C>(self, g: Circuit<C, B>) -> Circuit<Either<A, C>, B>
-    where
-        A: 'static,
-        B: 'static,
-        C: 'static,
-    {
-        self.split(g).then_pure(|either| match either {
-            Either::Left(x) => x,
-            Either::Right(y) => y,
-        })
+impl<'a, A, B, I> Run<'a, A, B, I> {
+    /// The circuit as it is after the inputs consumed so far.
+    pub fn into_circuit(self) -> Circuit<'a, A, B> {
+        self.circuit
     }
 }
 
-impl<F, A, B> From<F> for Circuit<A, B>
+impl<A, B, I> Iterator for Run<'_, A, B, I>
 where
-    F: FnOnce(A) -> B + Clone + 'static,
+    I: Iterator<Item = A>,
 {
-    fn from(f: F) -> Self {
-        let inner = move |a| (Self::from(f.clone()), f(a));
-        Circuit::new(inner)
-    }
-}
+    type Item = B;
 
-pub fn arrow<F, A, B>(f: F) -> Circuit<A, B>
-where
-    F: FnOnce(A) -> B + Clone + 'static,
-{
-    f.into()
-}
+    fn next(&mut self) -> Option<B> {
+        let a = self.inputs.next()?;
+        Some(self.circuit.step(a))
+    }
 
-impl<A> Circuit<A, A> {
-    #[cfg(test)]
-    fn id() -> Self {
-        CircuitFamily::id()
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.inputs.size_hint()
     }
 }
 
-impl<A, B> Circuit<A, B> {
-    fn new<F>(f: F) -> Self
-    where
-        F: FnOnce(A) -> (Self, B) + 'static,
-    {
-        Self { step: Box::new(f) }
+/// Runs the items of any iterator through a circuit.
+pub trait IteratorExt: Iterator + Sized {
+    fn through<'a, B>(self, circuit: Circuit<'a, Self::Item, B>) -> Run<'a, Self::Item, B, Self> {
+        circuit.run(self)
     }
+}
 
-    fn call(self, a: A) -> (Self, B) {
-        (self.step)(a)
-    }
+impl<I: Iterator> IteratorExt for I {}
 
-    #[cfg(test)]
-    fn output(self, a: A) -> B {
-        self.call(a).1
-    }
+/// Holds a circuit between steps whose timing is decided elsewhere, e.g. by
+/// an event loop or a timer interrupt.
+pub struct CircuitDriver<'a, A, B> {
+    circuit: Circuit<'a, A, B>,
+}
 
-    #[cfg(test)]
-    fn run(self, xs: impl IntoIterator<Item = A>) -> impl Iterator<Item = B> {
-        xs.into_iter().scan(Some(self), |circ, x| {
-            let (circ_new, item) = circ.take()?.call(x);
-            *circ = Some(circ_new);
-            Some(item)
-        })
+impl<'a, A, B> CircuitDriver<'a, A, B> {
+    pub fn new(circuit: Circuit<'a, A, B>) -> Self {
+        Self { circuit }
     }
 
-    #[cfg(test)]
-    fn accum<Acc, F>(acc: Acc, mut f: F) -> Self
-    where
-        Acc: 'static,
-        F: FnMut(A, Acc) -> (B, Acc) + 'static,
-    {
-        let g = |a| {
-            let (res, acc_new) = f(a, acc);
-            (Self::accum(acc_new, f), res)
-        };
-        Self::new(g)
+    /// Feeds one input through the circuit and keeps its next state.
+    pub fn step(&mut self, a: A) -> B {
+        self.circuit.step(a)
     }
 
-    #[cfg(test)]
-    fn accum_dup<F>(acc: B, mut f: F) -> Self
-    where
-        F: FnMut(A, B) -> B + 'static,
-        B: Clone + 'static,
-    {
-        Self::accum(acc, move |a, b| {
-            let b_new = f(a, b);
-            (b_new.clone(), b_new)
-        })
+    pub fn into_circuit(self) -> Circuit<'a, A, B> {
+        self.circuit
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::impls::either::Either;
     use rand::{thread_rng, Rng};
     use std::ops;
 
@@ -307,11 +159,120 @@ mod tests {
         assert_eq!(inc_even_else_none(4), Some(5));
     }
 
-    fn total() -> Circuit<usize, usize> {
+    #[test]
+    fn test_arrow_not_clone() {
+        // a closure capturing a value that can't be cloned is shared between
+        // steps instead of copied
+        struct Scale(usize);
+        let scale = Scale(3);
+        let circ = arrow(move |n: usize| n * scale.0);
+        let res: Vec<_> = circ.run(vec![1, 2, 3]).collect();
+        assert_eq!(res, vec![3, 6, 9]);
+    }
+
+    #[test]
+    fn test_borrowed() {
+        // the circuit borrows the stop words and passes slices of the text
+        let text = String::from("the quick fox and the lazy dog");
+        let stop = [String::from("the"), String::from("and")];
+        let is_stop = |(word, _): &(&str, usize)| stop.iter().any(|s| s == word);
+        let count = arrow(|word: &str| (word, word.len()))
+            .then(cond(is_stop, arrow(|_| 0), arrow(|(_, len)| len)))
+            .then(Circuit::accum_dup(0, |len, total| total + len));
+        let totals: Vec<_> = count.run(text.split(' ')).collect();
+        assert_eq!(totals, vec![0, 5, 8, 8, 8, 12, 15]);
+    }
+
+    #[test]
+    fn test_long_composition() {
+        // deep enough to overflow the stack if each stage were a nested call
+        let mut circ = (0..100_000).fold(Circuit::id(), |circ, _| circ.then_pure(|n: u64| n + 1));
+        assert_eq!(circ.step(0), 100_000);
+        assert_eq!(circ.step(5), 100_005);
+
+        let sum = (0..100_000).fold(arrow(|n: u64| n), |circ, i| {
+            circ.then(Circuit::accum(i, |n, acc| (n + acc, acc)))
+        });
+        assert_eq!(sum.output(0), (0..100_000).sum::<u64>());
+
+        // composed from the other end, which must not copy the stages over
+        // on every `then`
+        let mut right =
+            (0..100_000).fold(Circuit::id(), |circ, _| arrow(|n: u64| n + 1).then(circ));
+        assert_eq!(right.step(1), 100_001);
+    }
+
+    #[test]
+    fn test_long_wrapping() {
+        let mut fst = (0..100_000).fold(Circuit::id(), |circ, _| {
+            circ.fst()
+                .then_pure(|(n, ()): (u64, ())| n + 1)
+                .after_pure(|n| (n, ()))
+        });
+        assert_eq!(fst.step(0), 100_000);
+        assert_eq!(fst.step(2), 100_002);
+
+        let mut dup = (0..100_000).fold(Circuit::id(), |circ, _| {
+            circ.dup(Circuit::id())
+                .then_pure(|(n, m): (u64, u64)| n.max(m) + 1)
+        });
+        assert_eq!(dup.step(0), 100_000);
+
+        // only the `Left` inputs go through the wrapped stages
+        let mut left = (0..100_000).fold(Circuit::id(), |circ, _| {
+            circ.left::<&str>()
+                .then_pure(|either| match either {
+                    Either::Left(n) => n + 1,
+                    Either::Right(_) => 0,
+                })
+                .after_pure(Either::Left)
+        });
+        assert_eq!(left.step(1u64), 100_001);
+
+        let mut counted = (0..100_000).fold(Circuit::id(), |circ, _| {
+            circ.fst()
+                .then_pure(|(n, count): (u64, u64)| (n + count, count + 1))
+                .loop_d(0)
+        });
+        assert_eq!(counted.run_collect([0, 0, 0]), vec![0, 100_000, 200_000]);
+    }
+
+    #[test]
+    fn test_generic() {
+        let parity = cond(
+            |n: &usize| n.is_multiple_of(2),
+            arrow(|n| format!("{} is even", n)),
+            arrow(|n| format!("{} is odd", n)),
+        );
+        assert_eq!(
+            parity.run([1, 2]).collect::<Vec<_>>(),
+            vec!["1 is odd", "2 is even"]
+        );
+
+        // the arrow returned by `left` has `fst`
+        let counted = left_with::<_, _, _, &str, _>(total());
+        let res: Vec<_> = counted
+            .run([
+                (Either::Left(2), 'a'),
+                (Either::Right("skip"), 'b'),
+                (Either::Left(3), 'c'),
+            ])
+            .collect();
+        assert_eq!(
+            res,
+            vec![
+                (Either::Left(2), 'a'),
+                (Either::Right("skip"), 'b'),
+                (Either::Left(5), 'c')
+            ]
+        );
+    }
+
+    fn total() -> Circuit<'static, usize, usize> {
         Circuit::accum_dup(0, ops::Add::add)
     }
 
-    fn oneshot() -> Circuit<(), bool> {
+    fn oneshot() -> Circuit<'static, (), bool> {
         Circuit::accum(true, |(), acc| (acc, false))
     }
 
@@ -359,4 +320,83 @@ mod tests {
         assert_eq!(repeat_word.len(), 1);
         assert!(dict.contains(&repeat_word[0].unwrap()));
     }
+
+    #[test]
+    fn test_proc() {
+        let dict = ["foo", "bar", "baz"];
+        let rng = thread_rng();
+        let range = 0..(dict.len() - 1);
+        let generator = Circuit::accum(rng, move |(), mut rng| (rng.gen_range(range.clone()), rng));
+        let pick_word = generator.then_pure(move |i| dict[i]);
+
+        // the get_word example from the haskell arrow tutorial
+        let get_word = crate::proc!(() => {
+            first_time <- oneshot() -< ();
+            m_picked <- if first_time {
+                picked <- pick_word -< ();
+                return_a -< Some(picked)
+            } else {
+                return_a -< None
+            };
+            m_word <- Circuit::accum_dup(None, Option::or) -< m_picked;
+            return_a -< m_word.unwrap()
+        });
+
+        let mut repeat_word: Vec<_> = get_word.run([(), (), (), ()]).collect();
+        repeat_word.dedup();
+        assert_eq!(repeat_word.len(), 1);
+        assert!(dict.contains(&repeat_word[0]));
+
+        // earlier bindings stay in scope, and let binds pure values
+        let stats = crate::proc!(n: usize => {
+            let double = n * 2;
+            sum <- total() -< double;
+            count <- total() -< 1;
+            let mean = sum / count;
+            return_a -< (n, mean)
+        });
+        let res: Vec<_> = stats.run([1, 2, 3]).collect();
+        assert_eq!(res, vec![(1, 2), (2, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn test_proc_match() {
+        let classify = crate::proc!(n => {
+            seen <- total() -< 1;
+            label <- match n % 3 {
+                0 => { return_a -< "fizz" }
+                1 => {
+                    ones <- total() -< 1;
+                    return_a -< if ones > 1 { "one again" } else { "one" }
+                }
+                _ => { return_a -< "other" }
+            };
+            return_a -< (seen, label)
+        });
+        let res: Vec<_> = classify.run([1, 2, 3, 4]).collect();
+        assert_eq!(
+            res,
+            vec![(1, "one"), (2, "other"), (3, "fizz"), (4, "one again")]
+        );
+    }
+
+    #[test]
+    fn test_loop() {
+        // a running sum built from feedback instead of an accumulator
+        let sum = arrow(|(n, acc): (usize, usize)| (n + acc, n + acc)).loop_d(0);
+        let res: Vec<_> = sum.run([1, 2, 3]).collect();
+        assert_eq!(res, vec![1, 3, 6]);
+
+        // fibonacci, with the previous two values fed back
+        let fib = crate::proc!(() => {
+            Circuit::id() -< ();
+            rec (a = 0, b = 1) {
+                let next = a + b;
+                (a, b) <- Circuit::id() -< (b, next);
+            }
+            return_a -< a
+        });
+        let res: Vec<usize> = fib.run([(); 6]).collect();
+        assert_eq!(res, vec![1, 1, 2, 3, 5, 8]);
+    }
 }
@@ -0,0 +1,234 @@
+//! Circuits that can be cloned along with their state.
+//!
+//! A `CloneCircuit` only stores closures and state that are `Clone`, so it
+//! can be forked, e.g. to try different inputs from the same point, or kept
+//! aside in its initial state to start over from. Its family only `Holds`
+//! `Clone` values, so the arrow traits take `Clone` functions and `loop_d`
+//! takes a `Clone` initial value, and a `CloneCircuit` converts into a
+//! `Circuit`.
+
+use crate::{
+    arrow::*,
+    impls::{
+        circuit::Circuit,
+        stages::{circuit_impls, Repr},
+    },
+};
+use alloc::{boxed::Box, vec::Vec};
+
+// `Clone` isn't object safe, so the boxed step clones itself into a new box.
+trait CloneStep<'a, A, B>: FnMut(A) -> B + 'a {
+    fn clone_box(&self) -> Box<dyn CloneStep<'a, A, B> + 'a>;
+}
+
+impl<'a, A, B, F> CloneStep<'a, A, B> for F
+where
+    F: FnMut(A) -> B + Clone + 'a,
+{
+    fn clone_box(&self) -> Box<dyn CloneStep<'a, A, B> + 'a> {
+        Box::new(self.clone())
+    }
+}
+
+impl<'a, A: 'a, B: 'a> Clone for Box<dyn CloneStep<'a, A, B> + 'a> {
+    fn clone(&self) -> Self {
+        // the box is a `CloneStep` too, whose `clone_box` would call this
+        (**self).clone_box()
+    }
+}
+
+pub struct CloneCircuitFamily;
+
+type Step<'a, A, B> = Box<dyn CloneStep<'a, A, B> + 'a>;
+
+// the stages of a `CloneCircuit` all run on the thread that steps it
+type Link<'a> = core::convert::Infallible;
+
+pub struct CloneCircuit<'a, A, B> {
+    repr: Repr<Step<'a, A, B>, Step<'a, bool, ()>, Link<'a>, A, B>,
+}
+
+circuit_impls!(CloneCircuit, CloneCircuitFamily, Clone);
+
+impl<'a, A: 'a, B: 'a> Clone for CloneCircuit<'a, A, B> {
+    fn clone(&self) -> Self {
+        Self {
+            repr: self.repr.clone(),
+        }
+    }
+}
+
+impl<'a, A: 'a> CloneCircuit<'a, A, A> {
+    // id :: cat a a
+    pub fn id() -> Self {
+        CloneCircuitFamily::id()
+    }
+}
+
+impl<'a, A: 'a, B: 'a> CloneCircuit<'a, A, B> {
+    /// A copy of the circuit in its current state, which steps independently
+    /// from then on.
+    pub fn fork(&self) -> Self {
+        self.clone()
+    }
+}
+
+impl<'a, A: 'a, B: 'a> From<CloneCircuit<'a, A, B>> for Circuit<'a, A, B> {
+    fn from(mut circuit: CloneCircuit<'a, A, B>) -> Self {
+        Circuit::from_step(move |a| circuit.step(a))
+    }
+}
+
+/// A circuit that keeps a copy of how it started, to go back to.
+pub struct Resettable<'a, A, B> {
+    initial: CloneCircuit<'a, A, B>,
+    current: CloneCircuit<'a, A, B>,
+}
+
+impl<'a, A: 'a, B: 'a> Resettable<'a, A, B> {
+    /// Remembers the circuit as it is now, as the state to reset to.
+    pub fn new(circuit: CloneCircuit<'a, A, B>) -> Self {
+        Self {
+            current: circuit.fork(),
+            initial: circuit,
+        }
+    }
+
+    pub fn step(&mut self, a: A) -> B {
+        self.current.step(a)
+    }
+
+    /// Goes back to the state the circuit was in when it was wrapped.
+    pub fn reset(&mut self) {
+        self.current = self.initial.fork();
+    }
+
+    /// The circuit in its current state.
+    pub fn circuit(&self) -> &CloneCircuit<'a, A, B> {
+        &self.current
+    }
+
+    pub fn into_circuit(self) -> CloneCircuit<'a, A, B> {
+        self.current
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::impls::either::Either;
+    use core::ops;
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn total() -> CloneCircuit<'static, usize, usize> {
+        CloneCircuit::accum_dup(0, ops::Add::add)
+    }
+
+    fn oneshot() -> CloneCircuit<'static, (), bool> {
+        CloneCircuit::accum(true, |(), acc| (acc, false))
+    }
+
+    // the get_word circuit from the hangman example, with a seeded generator
+    // so that forks pick the same words
+    fn get_word(seed: u64) -> CloneCircuit<'static, (), Option<&'static str>> {
+        let dict = ["foo", "bar", "baz"];
+        let rng = StdRng::seed_from_u64(seed);
+        let generator =
+            CloneCircuit::accum(rng, move |(), mut rng| (rng.gen_range(0..dict.len()), rng));
+        let pick_word = generator.then_pure(move |i| dict[i]);
+        oneshot()
+            .then_pure(Either::from)
+            .then(CloneCircuit::arrow(|_| None).owise(pick_word.then_pure(Some)))
+            .then(CloneCircuit::accum_dup(None, Option::or))
+    }
+
+    #[test]
+    fn test_fork() {
+        let mut mean = total()
+            .dup(CloneCircuit::arrow(|_| 1).then(total()))
+            .then_pure(|(sum, n)| sum / n);
+        assert_eq!(mean.run_collect([1, 5, 8]), vec![1, 3, 4]);
+
+        // what if the next input were different
+        let mut what_if = mean.fork();
+        assert_eq!(mean.run_collect([12, 100]), vec![6, 25]);
+        assert_eq!(what_if.run_collect([0, 0]), vec![3, 2]);
+
+        let mut word = get_word(7);
+        let first = word.step(());
+        let mut forked = word.fork();
+        assert_eq!(forked.run_collect([(), ()]), vec![first, first]);
+        assert_eq!(word.step(()), first);
+
+        let circuit = Circuit::from(total().then(CloneCircuit::arrow(|n: usize| n * 2)));
+        assert_eq!(circuit.run([1, 2]).collect::<Vec<_>>(), vec![2, 6]);
+    }
+
+    #[test]
+    fn test_generic() {
+        let parity = cond(
+            |n: &usize| n.is_multiple_of(2),
+            CloneCircuit::arrow(|n| format!("{} is even", n)),
+            CloneCircuit::arrow(|n| format!("{} is odd", n)),
+        );
+        assert_eq!(parity.output(2), "2 is even");
+
+        let mut counted = left_with::<_, _, _, &str, _>(total());
+        assert_eq!(counted.step((Either::Left(2), 'a')), (Either::Left(2), 'a'));
+        let mut forked = counted.fork();
+        assert_eq!(
+            counted.step((Either::Right("skip"), 'b')),
+            (Either::Right("skip"), 'b')
+        );
+        assert_eq!(counted.step((Either::Left(3), 'c')), (Either::Left(5), 'c'));
+        assert_eq!(forked.step((Either::Left(1), 'd')), (Either::Left(3), 'd'));
+
+        // fibonacci, with the previous two values fed back
+        let mut fib = crate::proc!(() => {
+            CloneCircuit::id() -< ();
+            rec (a = 0, b = 1) {
+                let next = a + b;
+                (a, b) <- CloneCircuit::id() -< (b, next);
+            }
+            return_a -< a
+        });
+        assert_eq!(fib.run_collect([(); 3]), vec![1, 1, 2]);
+        let mut from_here = fib.fork();
+        assert_eq!(fib.run_collect([(); 3]), vec![3, 5, 8]);
+        assert_eq!(from_here.run_collect([(); 3]), vec![3, 5, 8]);
+    }
+
+    #[test]
+    fn test_long_composition() {
+        // long enough to be flat, so the forks copy every stage
+        let mut counted = (0..100_000).fold(CloneCircuit::id(), |circ, _| {
+            circ.fst()
+                .then_pure(|(n, count): (u64, u64)| (n + count, count + 1))
+                .loop_d(0)
+        });
+        assert_eq!(counted.step(0), 0);
+        let mut forked = counted.fork();
+        assert_eq!(counted.run_collect([0, 0]), vec![100_000, 200_000]);
+        assert_eq!(forked.run_collect([1, 0]), vec![100_001, 200_000]);
+    }
+
+    #[test]
+    fn test_reset() {
+        let mut counter = Resettable::new(total().both(oneshot()));
+        assert_eq!(counter.step((2, ())), (2, true));
+        assert_eq!(counter.step((3, ())), (5, false));
+
+        // start over after a bad input
+        assert_eq!(counter.step((1000, ())), (1005, false));
+        counter.reset();
+        assert_eq!(counter.step((3, ())), (3, true));
+        assert_eq!(counter.circuit().fork().output((1, ())), (4, false));
+
+        // the words are picked again, in the same order
+        let mut word = Resettable::new(get_word(3));
+        let picked = word.step(());
+        word.reset();
+        assert_eq!(word.step(()), picked);
+        assert_eq!(word.into_circuit().output(()), picked);
+    }
+}
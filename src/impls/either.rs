@@ -2,67 +2,155 @@ use crate::monad::*;
 
 /// The Either monad behaves as you would expect Result to behave, except
 /// `Left(e)` represents errors by convention.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
 pub enum Either<L, R> {
     Left(L),
     Right(R),
 }
 
-pub struct EitherFamily<L>(std::marker::PhantomData<L>);
+pub struct EitherFamily<L>(core::marker::PhantomData<L>);
 
-impl<L> FunctorFamily for EitherFamily<L> {
+impl<L> Kind for EitherFamily<L> {
     type M<T> = Either<L, T>;
 }
 
-impl<L, R> Functor<R> for Either<L, R> {
-    type FFamily = EitherFamily<L>;
+impl<L, R> Hkt<R> for Either<L, R> {
+    type Kind = EitherFamily<L>;
+}
 
-    fn fmap<F, B>(self, f: F) -> Either<L, B>
+impl<'a, L> FunctorFamily<'a> for EitherFamily<L> {
+    fn fmap<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
     where
-        F: Fn(R) -> B,
+        F: Fn(A) -> B,
     {
-        match self {
-            Self::Right(r) => Either::Right(f(r)),
-            Self::Left(l) => Either::Left(l),
+        match either {
+            Either::Right(r) => Either::Right(f(r)),
+            Either::Left(l) => Either::Left(l),
         }
     }
 }
 
-impl<L> ApplicativeFamily for EitherFamily<L> {
-    type M<T> = Either<L, T>;
+impl<'a, L> MapMutFamily<'a> for EitherFamily<L> {
+    fn fmap_mut<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
+    where
+        F: FnMut(A) -> B,
+    {
+        Self::fmap_once(either, f)
+    }
 }
 
-impl<L, R> Applicative<R> for Either<L, R> {
-    type AFamily = EitherFamily<L>;
+impl<'a, L> MapOnceFamily<'a> for EitherFamily<L> {
+    fn fmap_once<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
+    where
+        F: FnOnce(A) -> B,
+    {
+        match either {
+            Either::Right(r) => Either::Right(f(r)),
+            Either::Left(l) => Either::Left(l),
+        }
+    }
+}
 
-    fn pure(value: R) -> Self {
-        Self::Right(value)
+// The `Left` side is cloned, as it has to be moved into the new `Either`.
+impl<'a, L: Clone> FunctorRefFamily<'a> for EitherFamily<L> {
+    fn fmap_ref<'r, A, B, F>(either: &'r Either<L, A>, mut f: F) -> Either<L, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        match either {
+            Either::Right(r) => Either::Right(f(r)),
+            Either::Left(l) => Either::Left(l.clone()),
+        }
     }
+}
 
-    fn lift_a2<F, B, C>(self, other: Either<L, B>, f: F) -> Either<L, C>
+impl<'a, L> FunctorMutFamily<'a> for EitherFamily<L> {
+    fn fmap_in_place<A, F>(either: &mut Either<L, A>, mut f: F)
     where
-        F: Fn(R, B) -> C,
+        F: FnMut(&mut A),
     {
-        match (self, other) {
-            (Self::Right(r_l), Either::Right(r_r)) => Either::Right(f(r_l, r_r)),
-            (Self::Left(left), _) | (_, Either::Left(left)) => Either::Left(left),
+        if let Either::Right(r) = either {
+            f(r)
         }
     }
 }
 
-impl<L> MonadFamily for EitherFamily<L> {
-    type M<T> = Either<L, T>;
+impl<L, R> Foldable<R> for Either<L, R> {
+    fn fold_left<'a, B, F>(&'a self, init: B, mut f: F) -> B
+    where
+        F: FnMut(B, &'a R) -> B,
+        R: 'a,
+    {
+        match self {
+            Self::Right(r) => f(init, r),
+            Self::Left(_) => init,
+        }
+    }
 }
 
-impl<L, R> Monad<R> for Either<L, R> {
-    type MFamily = EitherFamily<L>;
+impl<'a, L> ApplicativeFamily<'a> for EitherFamily<L> {
+    fn pure<A>(value: A) -> Either<L, A> {
+        Either::Right(value)
+    }
 
-    fn bind<F, B>(self, f: F) -> Either<L, B>
+    fn lift_a2<A, B, C, F>(either_a: Either<L, A>, either_b: Either<L, B>, f: F) -> Either<L, C>
     where
-        F: Fn(R) -> Either<L, B>,
+        F: Fn(A, B) -> C,
     {
-        match self {
-            Self::Right(right) => f(right),
-            Self::Left(left) => Either::Left(left),
+        match (either_a, either_b) {
+            (Either::Right(r_l), Either::Right(r_r)) => Either::Right(f(r_l, r_r)),
+            (Either::Left(left), _) | (_, Either::Left(left)) => Either::Left(left),
+        }
+    }
+}
+
+impl<'a, L> SelectiveFamily<'a> for EitherFamily<L> {
+    // select :: f (Either a b) -> f (a -> b) -> f b
+    fn select<A, B, F>(x: Either<L, Either<A, B>>, f: Either<L, F>) -> Either<L, B>
+    where
+        F: Fn(A) -> B,
+    {
+        match x {
+            Either::Right(Either::Left(a)) => match f {
+                Either::Right(f) => Either::Right(f(a)),
+                Either::Left(l) => Either::Left(l),
+            },
+            Either::Right(Either::Right(b)) => Either::Right(b),
+            Either::Left(l) => Either::Left(l),
+        }
+    }
+}
+
+impl<'a, L> MonadFamily<'a> for EitherFamily<L> {
+    fn bind<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
+    where
+        F: Fn(A) -> Either<L, B>,
+    {
+        match either {
+            Either::Right(right) => f(right),
+            Either::Left(left) => Either::Left(left),
+        }
+    }
+}
+
+impl<'a, L> BindMutFamily<'a> for EitherFamily<L> {
+    fn bind_mut<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
+    where
+        F: FnMut(A) -> Either<L, B>,
+    {
+        Self::bind_once(either, f)
+    }
+}
+
+impl<'a, L> BindOnceFamily<'a> for EitherFamily<L> {
+    fn bind_once<A, B, F>(either: Either<L, A>, f: F) -> Either<L, B>
+    where
+        F: FnOnce(A) -> Either<L, B>,
+    {
+        match either {
+            Either::Right(right) => f(right),
+            Either::Left(left) => Either::Left(left),
         }
     }
 }
@@ -101,3 +189,68 @@ impl From<bool> for Either<(), ()> {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_selective() {
+        type E<T> = Either<&'static str, T>;
+        let f: E<fn(usize) -> usize> = Either::Right(|x| x + 1);
+        let err: E<fn(usize) -> usize> = Either::Left("no function");
+
+        let left: E<Either<usize, usize>> = Either::Right(Either::Left(1));
+        let right: E<Either<usize, usize>> = Either::Right(Either::Right(5));
+        assert_eq!(EitherFamily::select(left, f), Either::Right(2));
+        assert_eq!(EitherFamily::select(left, err), Either::Left("no function"));
+        assert_eq!(EitherFamily::select(right, err), Either::Right(5));
+
+        let cond: E<bool> = Either::Left("no condition");
+        assert_eq!(
+            EitherFamily::if_s(cond, Either::Right(1), Either::Right(2)),
+            Either::Left("no condition")
+        );
+        assert_eq!(
+            EitherFamily::if_s(
+                Either::Right(false),
+                Either::Left("unused"),
+                Either::Right(2)
+            ),
+            Either::Right(2)
+        );
+    }
+
+    #[test]
+    fn test_closure_kinds() {
+        type E<T> = Either<&'static str, T>;
+        let word = String::from("owned");
+        let right: E<usize> = Either::Right(2);
+        assert_eq!(
+            right.fmap_once(move |n| word.repeat(n)),
+            Either::Right("ownedowned".to_string())
+        );
+
+        let word = String::from("owned");
+        let left: E<usize> = Either::Left("failed");
+        assert_eq!(
+            left.bind_once(move |_| Either::Right(word)),
+            Either::Left("failed")
+        );
+    }
+
+    #[test]
+    fn test_by_ref() {
+        let right: Either<String, Vec<usize>> = Either::Right(vec![1, 2]);
+        assert_eq!(right.fmap_ref(Vec::len), Either::Right(2));
+
+        let mut left: Either<String, Vec<usize>> = Either::Left("failed".to_string());
+        assert_eq!(left.fmap_ref(Vec::len), Either::Left("failed".to_string()));
+        left.fmap_in_place(Vec::clear);
+        assert_eq!(left, Either::Left("failed".to_string()));
+
+        let mut right = right;
+        right.fmap_in_place(|xs| xs.push(3));
+        assert_eq!(right, Either::Right(vec![1, 2, 3]));
+    }
+}
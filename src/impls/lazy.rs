@@ -0,0 +1,511 @@
+use crate::monad::*;
+use alloc::{boxed::Box, rc::Rc, vec::Vec};
+use core::{
+    cell::{Cell, OnceCell, RefCell},
+    marker::PhantomData,
+    mem,
+};
+
+/// A deferred, memoized computation.
+///
+/// Nothing is evaluated until the value is forced, after which the result is
+/// cached, so forcing it again is free. The computation may borrow anything
+/// that outlives `'a`, which is how several computations share one `Lazy`:
+/// each borrows it and forces it when it needs the value.
+pub struct Lazy<'a, A> {
+    value: OnceCell<A>,
+    init: Cell<Option<Init<'a, A>>>,
+}
+
+type Init<'a, A> = Box<dyn FnOnce() -> A + 'a>;
+
+impl<'a, A> Lazy<'a, A> {
+    pub fn new<F>(f: F) -> Self
+    where
+        F: FnOnce() -> A + 'a,
+    {
+        Self {
+            value: OnceCell::new(),
+            init: Cell::new(Some(Box::new(f))),
+        }
+    }
+
+    // a -> f a
+    pub fn pure(a: A) -> Self {
+        Self {
+            value: OnceCell::from(a),
+            init: Cell::new(None),
+        }
+    }
+
+    /// Evaluates the computation if it has not been evaluated yet.
+    ///
+    /// Panics if the computation forces itself.
+    pub fn force(&self) -> &A {
+        self.value.get_or_init(|| {
+            let init = self.init.take();
+            init.expect("Lazy value forced itself during evaluation")()
+        })
+    }
+
+    pub fn is_forced(&self) -> bool {
+        self.value.get().is_some()
+    }
+
+    /// Forces the computation and takes the result.
+    pub fn into_value(mut self) -> A {
+        self.take()
+    }
+
+    // forces the computation and moves the result out, leaving nothing to
+    // force
+    fn take(&mut self) -> A {
+        self.force();
+        self.value.take().unwrap()
+    }
+}
+
+/// The family of `Lazy<'a, _>`. Mapped and bound functions are kept until
+/// the value is forced, so they must outlive `'a`.
+pub struct LazyFamily<'a>(PhantomData<&'a ()>);
+
+impl<'a> Kind for LazyFamily<'a> {
+    type M<T> = Lazy<'a, T>;
+}
+impl<'a, A> Hkt<A> for Lazy<'a, A> {
+    type Kind = LazyFamily<'a>;
+}
+
+impl<'a> FunctorFamily<'a> for LazyFamily<'a> {
+    // fmap (<$>) :: (a -> b) -> f a -> f b
+    fn fmap<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::fmap_once(lazy, f)
+    }
+}
+impl<'a> MapMutFamily<'a> for LazyFamily<'a> {
+    fn fmap_mut<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: FnMut(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::fmap_once(lazy, f)
+    }
+}
+impl<'a> MapOnceFamily<'a> for LazyFamily<'a> {
+    fn fmap_once<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: FnOnce(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Lazy::new(move || f(lazy.into_value()))
+    }
+}
+// The value is updated when it is forced. There is no `FunctorRefFamily`,
+// as its result would have to keep borrowing `lazy` for all of `'a`, or
+// force it.
+impl<'a> FunctorMutFamily<'a> for LazyFamily<'a> {
+    fn fmap_in_place<A, F>(lazy: &mut Lazy<'a, A>, mut f: F)
+    where
+        F: FnMut(&mut A) + 'a,
+        A: 'a,
+    {
+        let taken = Lazy {
+            value: OnceCell::new(),
+            init: Cell::new(None),
+        };
+        let old = mem::replace(lazy, taken);
+        *lazy = Self::fmap_mut(old, move |mut a| {
+            f(&mut a);
+            a
+        });
+    }
+}
+impl<'a> ApplicativeFamily<'a> for LazyFamily<'a> {
+    // a -> f a
+    fn pure<A>(a: A) -> Lazy<'a, A> {
+        Lazy::pure(a)
+    }
+
+    // (a -> b -> c) -> f a -> f b -> f c
+    fn lift_a2<A, B, C, F>(lazy_a: Lazy<'a, A>, lazy_b: Lazy<'a, B>, f: F) -> Lazy<'a, C>
+    where
+        F: Fn(A, B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        Lazy::new(move || f(lazy_a.into_value(), lazy_b.into_value()))
+    }
+}
+impl<'a> MonadFamily<'a> for LazyFamily<'a> {
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: Fn(A) -> Lazy<'a, B> + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::bind_once(lazy, f)
+    }
+}
+impl<'a> BindMutFamily<'a> for LazyFamily<'a> {
+    fn bind_mut<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: FnMut(A) -> Lazy<'a, B> + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::bind_once(lazy, f)
+    }
+}
+impl<'a> BindOnceFamily<'a> for LazyFamily<'a> {
+    fn bind_once<A, B, F>(lazy: Lazy<'a, A>, f: F) -> Lazy<'a, B>
+    where
+        F: FnOnce(A) -> Lazy<'a, B> + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Lazy::new(move || f(lazy.into_value()).into_value())
+    }
+}
+
+/// A possibly infinite list whose cells are only evaluated when reached.
+pub struct Stream<'a, A> {
+    cell: Box<StreamCell<'a, A>>,
+}
+
+type StreamCell<'a, A> = Lazy<'a, Option<(A, Stream<'a, A>)>>;
+
+impl<'a, A> Stream<'a, A> {
+    pub fn empty() -> Self {
+        Self {
+            cell: Box::new(Lazy::pure(None)),
+        }
+    }
+
+    pub fn cons(head: A, tail: Lazy<'a, Stream<'a, A>>) -> Self
+    where
+        A: 'a,
+    {
+        Self {
+            cell: Box::new(Lazy::new(move || Some((head, tail.into_value())))),
+        }
+    }
+
+    pub fn unfold<S, F>(seed: S, f: F) -> Self
+    where
+        S: 'a,
+        F: Fn(S) -> Option<(A, S)> + 'a,
+    {
+        Self::unfold_rc(seed, Rc::new(f))
+    }
+
+    fn unfold_rc<S, F>(seed: S, f: Rc<F>) -> Self
+    where
+        S: 'a,
+        F: Fn(S) -> Option<(A, S)> + 'a,
+    {
+        let cell = Lazy::new(move || {
+            let (a, seed_next) = f(seed)?;
+            Some((a, Self::unfold_rc(seed_next, f)))
+        });
+        Self {
+            cell: Box::new(cell),
+        }
+    }
+
+    pub fn head(&self) -> Option<&A> {
+        self.cell.force().as_ref().map(|(head, _)| head)
+    }
+
+    pub fn tail(&self) -> Option<&Stream<'a, A>> {
+        self.cell.force().as_ref().map(|(_, tail)| tail)
+    }
+
+    pub fn zip_with<F, B, C>(self, other: Stream<'a, B>, f: F) -> Stream<'a, C>
+    where
+        F: Fn(A, B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Stream::unfold((self, other), move |(left, right)| {
+            let (a, left_tail) = left.uncons()?;
+            let (b, right_tail) = right.uncons()?;
+            Some((f(a, b), (left_tail, right_tail)))
+        })
+    }
+
+    pub fn filter<P>(self, p: P) -> Self
+    where
+        P: Fn(&A) -> bool + 'a,
+        A: 'a,
+    {
+        Self::unfold(self, move |mut stream| loop {
+            let (a, tail) = stream.uncons()?;
+            if p(&a) {
+                return Some((a, tail));
+            }
+            stream = tail;
+        })
+    }
+
+    /// Iterates over the stream by reference, forcing each cell as it is
+    /// reached.
+    pub fn iter(&self) -> StreamIter<'_, 'a, A> {
+        StreamIter { stream: self }
+    }
+
+    // the head and tail, moved out of the cell
+    fn uncons(mut self) -> Option<(A, Stream<'a, A>)> {
+        self.cell.take()
+    }
+
+    // the tail of a forced cell
+    fn take_tail(&mut self) -> Option<Stream<'a, A>> {
+        let (_, tail) = self.cell.value.take()??;
+        Some(tail)
+    }
+}
+
+// Each forced cell owns the next, so dropping a long stream would drop the
+// cells one inside the other; they are unlinked one at a time instead.
+impl<A> Drop for Stream<'_, A> {
+    fn drop(&mut self) {
+        let mut next = self.take_tail();
+        while let Some(mut stream) = next {
+            next = stream.take_tail();
+        }
+    }
+}
+
+impl<'a, A> Stream<'a, A>
+where
+    A: Clone + 'a,
+{
+    // iterate :: (a -> a) -> a -> [a]
+    pub fn iterate<F>(seed: A, f: F) -> Self
+    where
+        F: Fn(A) -> A + 'a,
+    {
+        Self::unfold(seed, move |a: A| Some((a.clone(), f(a))))
+    }
+
+    pub fn repeat(a: A) -> Self {
+        Self::unfold((), move |()| Some((a.clone(), ())))
+    }
+
+    pub fn take(&self, n: usize) -> Vec<A> {
+        self.iter().take(n).cloned().collect()
+    }
+}
+
+pub struct StreamIter<'s, 'a, A> {
+    stream: &'s Stream<'a, A>,
+}
+
+impl<'s, A> Iterator for StreamIter<'s, '_, A> {
+    type Item = &'s A;
+
+    fn next(&mut self) -> Option<&'s A> {
+        let (head, tail) = self.stream.cell.force().as_ref()?;
+        self.stream = tail;
+        Some(head)
+    }
+}
+
+/// The family of `Stream<'a, _>`, which is only a functor: a lawful `pure`
+/// would repeat its value and a lawful `lift_a2` would pair every element
+/// with every other, and both would copy elements that the traits don't ask
+/// to be `Clone`. `zip_with` stands in for `lift_a2`.
+pub struct StreamFamily<'a>(PhantomData<&'a ()>);
+
+impl<'a> Kind for StreamFamily<'a> {
+    type M<T> = Stream<'a, T>;
+}
+impl<'a, A> Hkt<A> for Stream<'a, A> {
+    type Kind = StreamFamily<'a>;
+}
+
+impl<'a> FunctorFamily<'a> for StreamFamily<'a> {
+    // fmap (<$>) :: (a -> b) -> f a -> f b
+    fn fmap<A, B, F>(stream: Stream<'a, A>, f: F) -> Stream<'a, B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Stream::unfold(stream, move |stream| {
+            let (a, tail) = stream.uncons()?;
+            Some((f(a), tail))
+        })
+    }
+}
+impl<'a> MapMutFamily<'a> for StreamFamily<'a> {
+    // a cell is only reached through the one before it, so the elements
+    // are mapped in order, each once
+    fn fmap_mut<A, B, F>(stream: Stream<'a, A>, f: F) -> Stream<'a, B>
+    where
+        F: FnMut(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        let f = RefCell::new(f);
+        Self::fmap(stream, move |a| (f.borrow_mut())(a))
+    }
+}
+// The elements are updated as they are reached. As with `Lazy`, there is
+// no `FunctorRefFamily`.
+impl<'a> FunctorMutFamily<'a> for StreamFamily<'a> {
+    fn fmap_in_place<A, F>(stream: &mut Stream<'a, A>, mut f: F)
+    where
+        F: FnMut(&mut A) + 'a,
+        A: 'a,
+    {
+        let old = mem::replace(stream, Stream::empty());
+        *stream = Self::fmap_mut(old, move |mut a| {
+            f(&mut a);
+            a
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lazy() {
+        let evals = Rc::new(Cell::new(0));
+        let counter = evals.clone();
+        let expensive = Lazy::new(move || {
+            counter.set(counter.get() + 1);
+            21
+        });
+
+        // functor, applicative and monad chains are deferred, and each
+        // borrows the value before it
+        let doubled = Lazy::new(|| *expensive.force()).fmap(|x| x * 2);
+        let summed = Lazy::new(|| *doubled.force()).lift_a2(Lazy::pure(1), |a, b| a + b);
+        let bound = Lazy::new(|| *summed.force()).bind(|n| Lazy::new(move || n.to_string()));
+        assert_eq!(evals.get(), 0);
+        assert!(!doubled.is_forced());
+
+        // forcing evaluates the shared source exactly once
+        assert_eq!(bound.force(), "43");
+        assert_eq!(*doubled.force(), 42);
+        assert_eq!(*expensive.force(), 21);
+        assert_eq!(evals.get(), 1);
+        assert!(summed.is_forced());
+
+        let double_lazy = Lazy::new(|| Lazy::new(|| 5));
+        assert_eq!(Lazy::join(double_lazy).into_value(), 5);
+
+        // a value that isn't Clone is moved through, and closures may borrow
+        struct Token(String);
+        let suffix = String::from("!");
+        let token = Lazy::pure(Token("hi".to_string())).fmap(|token| Token(token.0 + &suffix));
+        assert_eq!(token.into_value().0, "hi!");
+    }
+
+    #[test]
+    fn test_closure_kinds() {
+        let greeting = String::from("hi");
+        let lazy = Lazy::pure('!')
+            .fmap_once(|c| greeting + &c.to_string())
+            .bind_once(|s| Lazy::new(move || s.len()));
+        assert_eq!(lazy.into_value(), 3);
+
+        let mut calls = 0;
+        let counted = Lazy::pure(2).fmap_mut(|n| {
+            calls += 1;
+            n * 10
+        });
+        assert_eq!(counted.into_value(), 20);
+        assert_eq!(calls, 1);
+
+        let mut seen = Vec::new();
+        let three = Stream::unfold(0, |i| (i < 3).then_some((i, i + 1)));
+        let logged = three.fmap_mut(|n| {
+            seen.push(n);
+            n
+        });
+        assert_eq!(logged.take(3), vec![0, 1, 2]);
+        drop(logged);
+        assert_eq!(seen, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_stream() {
+        let naturals = Stream::iterate(0usize, |n| n + 1);
+        assert_eq!(naturals.take(5), vec![0, 1, 2, 3, 4]);
+
+        let evens = Stream::iterate(0usize, |n| n + 1).filter(|n| n % 2 == 0);
+        assert_eq!(evens.fmap(|n| n * 10).take(3), vec![0, 20, 40]);
+
+        let fibs = Stream::unfold((0u64, 1u64), |(a, b)| Some((a, (b, a + b))));
+        assert_eq!(fibs.take(8), vec![0, 1, 1, 2, 3, 5, 8, 13]);
+
+        let pairs = naturals.zip_with(Stream::repeat('x'), |n, c| (n, c));
+        assert_eq!(pairs.tail().unwrap().head(), Some(&(1, 'x')));
+
+        let finite = Stream::cons(
+            1,
+            Lazy::new(|| Stream::cons(2, Lazy::pure(Stream::empty()))),
+        );
+        assert_eq!(finite.iter().collect::<Vec<_>>(), vec![&1, &2]);
+        assert_eq!(Stream::<u8>::empty().head(), None);
+    }
+
+    #[test]
+    fn test_in_place() {
+        let mut shouted = Lazy::new(|| "lazy".to_string());
+        shouted.fmap_in_place(|s| s.make_ascii_uppercase());
+        assert!(!shouted.is_forced());
+        assert_eq!(shouted.force(), "LAZY");
+
+        let mut words = Stream::unfold(0, |i| {
+            let word = ["a", "bb", "ccc"].get(i)?;
+            Some((word.to_string(), i + 1))
+        });
+        words.fmap_in_place(|w| w.push('!'));
+        assert_eq!(words.take(3), vec!["a!", "bb!", "ccc!"]);
+
+        // updating in place is as lazy as the stream
+        let mut naturals = Stream::iterate(0u64, |n| n + 1);
+        naturals.fmap_in_place(|n| *n *= 2);
+        assert_eq!(naturals.take(3), vec![0, 2, 4]);
+    }
+
+    #[test]
+    fn test_stream_memoized() {
+        let evals = Rc::new(Cell::new(0));
+        let counter = evals.clone();
+        let stream = Stream::iterate(1, move |n| {
+            counter.set(counter.get() + 1);
+            n * 2
+        });
+        assert_eq!(evals.get(), 0);
+        assert_eq!(stream.take(4), vec![1, 2, 4, 8]);
+        assert_eq!(stream.take(4), vec![1, 2, 4, 8]);
+        assert_eq!(evals.get(), 4);
+    }
+
+    #[test]
+    fn test_long_stream() {
+        // dropping the forced cells one inside the other would overflow the
+        // stack
+        let naturals = Stream::iterate(0u64, |n| n + 1);
+        assert_eq!(naturals.iter().nth(100_000), Some(&100_000));
+        drop(naturals);
+
+        let evens = Stream::iterate(0u64, |n| n + 1).filter(|n| n % 2 == 0);
+        assert_eq!(evens.iter().nth(100_000), Some(&200_000));
+    }
+}
@@ -1,4 +1,23 @@
+pub mod analysis;
+#[cfg(feature = "alloc")]
+pub mod cca;
+#[cfg(feature = "alloc")]
 pub mod circuit;
+#[cfg(feature = "alloc")]
+pub mod clone_circuit;
+pub mod either;
+#[cfg(feature = "alloc")]
+pub mod lazy;
 pub mod option;
+#[cfg(feature = "std")]
+pub mod pipeline;
+#[cfg(feature = "alloc")]
+pub mod recorder;
+#[cfg(feature = "alloc")]
+pub mod send_circuit;
+#[cfg(feature = "alloc")]
+mod stages;
+pub mod static_circuit;
+pub mod validation;
+#[cfg(feature = "alloc")]
 pub mod vec;
-pub mod either;
@@ -1,65 +1,113 @@
-use crate::monad::*;
+use crate::{impls::either::Either, monad::*};
 
 pub struct OptionFamily;
 
-impl FunctorFamily for OptionFamily {
+impl Kind for OptionFamily {
     type M<T> = Option<T>;
 }
-impl<A> Functor<A> for Option<A> {
-    type FFamily = OptionFamily;
+impl<A> Hkt<A> for Option<A> {
+    type Kind = OptionFamily;
+}
 
+impl<'a> FunctorFamily<'a> for OptionFamily {
     // fmap (<$>) :: (a -> b) -> f a -> f b
-    fn fmap<F, B>(self, f: F) -> Option<B>
+    fn fmap<A, B, F>(opt: Option<A>, f: F) -> Option<B>
     where
         F: Fn(A) -> B,
     {
-        self.map(f)
+        opt.map(f)
     }
 }
-impl ApplicativeFamily for OptionFamily {
-    type M<T> = Option<T>;
+impl<'a> MapMutFamily<'a> for OptionFamily {
+    fn fmap_mut<A, B, F>(opt: Option<A>, f: F) -> Option<B>
+    where
+        F: FnMut(A) -> B,
+    {
+        opt.map(f)
+    }
 }
-impl<A> Applicative<A> for Option<A> {
-    type AFamily = OptionFamily;
-
+impl<'a> MapOnceFamily<'a> for OptionFamily {
+    fn fmap_once<A, B, F>(opt: Option<A>, f: F) -> Option<B>
+    where
+        F: FnOnce(A) -> B,
+    {
+        opt.map(f)
+    }
+}
+impl<'a> FunctorRefFamily<'a> for OptionFamily {
+    fn fmap_ref<'r, A, B, F>(opt: &'r Option<A>, f: F) -> Option<B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        opt.as_ref().map(f)
+    }
+}
+impl<'a> FunctorMutFamily<'a> for OptionFamily {
+    fn fmap_in_place<A, F>(opt: &mut Option<A>, f: F)
+    where
+        F: FnMut(&mut A),
+    {
+        opt.iter_mut().for_each(f)
+    }
+}
+impl<A> Foldable<A> for Option<A> {
+    // foldl :: (b -> a -> b) -> b -> t a -> b
+    fn fold_left<'a, B, F>(&'a self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &'a A) -> B,
+        A: 'a,
+    {
+        self.iter().fold(init, f)
+    }
+}
+impl<'a> ApplicativeFamily<'a> for OptionFamily {
     // a -> f a
-    fn pure(a: A) -> Self {
+    fn pure<A>(a: A) -> Option<A> {
         Some(a)
     }
 
     // (a -> b -> c) -> f a -> f b -> f c
-    fn lift_a2<F, B, C>(self, opt_b: Option<B>, f: F) -> Option<C>
+    fn lift_a2<A, B, C, F>(opt_a: Option<A>, opt_b: Option<B>, f: F) -> Option<C>
     where
         F: Fn(A, B) -> C,
     {
-        Some(f(self?, opt_b?))
+        Some(f(opt_a?, opt_b?))
     }
 
     // (<*>) :: f (a -> b) -> f a -> f b
-    fn apply<F, B>(self, maybe_fn: Option<F>) -> Option<B>
+    fn apply<A, B, F>(opt: Option<A>, maybe_fn: Option<F>) -> Option<B>
     where
         F: Fn(A) -> B,
     {
-        Some((maybe_fn?)(self?))
+        Some((maybe_fn?)(opt?))
     }
 }
 
-impl MonadFamily for OptionFamily {
-    type M<T> = Option<T>;
+impl<'a> SelectiveFamily<'a> for OptionFamily {
+    // select :: f (Either a b) -> f (a -> b) -> f b
+    fn select<A, B, F>(x: Option<Either<A, B>>, f: Option<F>) -> Option<B>
+    where
+        F: Fn(A) -> B,
+    {
+        match x? {
+            Either::Left(a) => Some((f?)(a)),
+            Either::Right(b) => Some(b),
+        }
+    }
 }
-impl<A> Monad<A> for Option<A> {
-    type MFamily = OptionFamily;
 
+impl<'a> MonadFamily<'a> for OptionFamily {
     // bind (>>=) :: m a -> (a -> m b) -> m b
-    fn bind<F, B>(self, f: F) -> Option<B>
+    fn bind<A, B, F>(opt: Option<A>, f: F) -> Option<B>
     where
         F: Fn(A) -> Option<B>,
     {
-        self.and_then(f)
+        opt.and_then(f)
     }
 
     // compose (>=>) :: (a -> m b) -> (b -> m c) -> a -> m c
-    fn compose<F, G, B, C>(f: F, g: G, a: A) -> Option<C>
+    fn compose<A, B, C, F, G>(f: F, g: G, a: A) -> Option<C>
     where
         F: FnOnce(A) -> Option<B>,
         G: Fn(B) -> Option<C>,
@@ -68,11 +116,38 @@ impl<A> Monad<A> for Option<A> {
     }
 
     // m (m a) -> m a
-    fn join(opt_opt: Option<Option<A>>) -> Self
+    fn join<A>(opt_opt: Option<Option<A>>) -> Option<A> {
+        opt_opt.and_then(core::convert::identity)
+    }
+}
+
+impl<'a> BindMutFamily<'a> for OptionFamily {
+    fn bind_mut<A, B, F>(opt: Option<A>, f: F) -> Option<B>
     where
-        Self: Sized,
+        F: FnMut(A) -> Option<B>,
     {
-        opt_opt.and_then(std::convert::identity)
+        opt.and_then(f)
+    }
+}
+
+impl<'a> BindOnceFamily<'a> for OptionFamily {
+    fn bind_once<A, B, F>(opt: Option<A>, f: F) -> Option<B>
+    where
+        F: FnOnce(A) -> Option<B>,
+    {
+        opt.and_then(f)
+    }
+}
+
+impl<'a> MonadPlusFamily<'a> for OptionFamily {
+    // mzero :: m a
+    fn mzero<A>() -> Option<A> {
+        None
+    }
+
+    // mplus :: m a -> m a -> m a
+    fn mplus<A>(a: Option<A>, b: Option<A>) -> Option<A> {
+        a.or(b)
     }
 }
 
@@ -107,4 +182,64 @@ mod tests {
         let double_opt = Some(opt);
         assert_eq!(Option::join(double_opt), opt);
     }
+
+    #[test]
+    fn test_selective() {
+        let left: Option<Either<usize, usize>> = Some(Either::Left(1));
+        let right: Option<Either<usize, usize>> = Some(Either::Right(1));
+        let f = Some(|x| x + 10);
+        let f_none: Option<fn(usize) -> usize> = None;
+
+        assert_eq!(OptionFamily::select(left, f), Some(11));
+        assert_eq!(OptionFamily::select(left, f_none), None);
+        // the function's effect is skipped on Right
+        assert_eq!(OptionFamily::select(right, f_none), Some(1));
+        // unlike select_a, which runs it anyway
+        assert_eq!(OptionFamily::select_a(right, f_none), None);
+        assert_eq!(OptionFamily::select_a(left, f), Some(11));
+
+        assert_eq!(OptionFamily::if_s(Some(true), Some(1), None), Some(1));
+        assert_eq!(OptionFamily::if_s(Some(false), Some(1), None), None);
+        assert_eq!(OptionFamily::if_s(None, Some(1), Some(2)), None);
+
+        assert_eq!(OptionFamily::when_s(Some(false), None), Some(()));
+        assert_eq!(OptionFamily::when_s(Some(true), None), None);
+
+        let branch = |x| OptionFamily::branch(Some(x), Some(|n: usize| n * 2), Some(str::len));
+        assert_eq!(branch(Either::Left(4)), Some(8));
+        assert_eq!(branch(Either::Right("abc")), Some(3));
+    }
+
+    #[test]
+    fn test_closure_kinds() {
+        // the name is moved out of the closure, so it is only FnOnce
+        let name = String::from("arrows");
+        let res = Some(1).fmap_once(move |n| format!("{name}{n}"));
+        assert_eq!(res, Some("arrows1".to_string()));
+
+        let name = String::from("arrows");
+        let res = Some(1).bind_once(move |n| (n > 0).then_some(name));
+        assert_eq!(res, Some("arrows".to_string()));
+
+        let mut calls = 0;
+        let none: Option<usize> = None;
+        assert_eq!(
+            none.fmap_mut(|n| {
+                calls += 1;
+                n
+            }),
+            None
+        );
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn test_by_ref() {
+        let name = Some(String::from("arrows"));
+        assert_eq!(name.fmap_ref(String::len), Some(6));
+
+        let mut name = name;
+        name.fmap_in_place(|s| s.make_ascii_uppercase());
+        assert_eq!(name.as_deref(), Some("ARROWS"));
+    }
 }
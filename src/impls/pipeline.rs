@@ -0,0 +1,209 @@
+//! Circuits split across threads, one thread per stage.
+//!
+//! A `Pipeline` runs each of its stages on a new thread, connected to the
+//! one before by a bounded channel, so while one stage steps on an input the
+//! next stage steps on the previous one. Outputs come out in input order,
+//! and each stage keeps its own state between inputs as it would in a single
+//! circuit. A stage composed with `SendCircuit::pipe` is split into more
+//! threads where it was piped, and `SendCircuit::pipelined` runs a single
+//! circuit that way.
+
+use crate::impls::send_circuit::SendCircuit;
+use std::{
+    panic,
+    sync::mpsc::{self, Receiver},
+    thread::{self, JoinHandle},
+};
+
+type Threads = Vec<JoinHandle<()>>;
+type Spawn<A, B> = Box<dyn FnOnce(Receiver<A>, usize, &mut Threads) -> Receiver<B>>;
+
+/// A chain of circuits that each run on their own thread.
+pub struct Pipeline<A, B> {
+    spawn: Spawn<A, B>,
+}
+
+impl<A, B> Pipeline<A, B>
+where
+    A: Send + 'static,
+    B: Send + 'static,
+{
+    /// A pipeline with `stage` as its only thread.
+    pub fn new(stage: SendCircuit<'static, A, B>) -> Self {
+        Self {
+            spawn: Box::new(|inputs, bound, threads| stage.spawn_parts(inputs, bound, threads)),
+        }
+    }
+
+    /// Runs `stage` on a new thread, after the threads already in the
+    /// pipeline.
+    pub fn then<C>(self, stage: SendCircuit<'static, B, C>) -> Pipeline<A, C>
+    where
+        C: Send + 'static,
+    {
+        Pipeline {
+            spawn: Box::new(move |inputs, bound, threads| {
+                let mid = (self.spawn)(inputs, bound, threads);
+                stage.spawn_parts(mid, bound, threads)
+            }),
+        }
+    }
+
+    /// Starts a thread for each stage, and one that sends them `xs`. At most
+    /// `bound` values wait between two stages at a time.
+    pub fn run<I>(self, bound: usize, xs: I) -> PipelineRun<B>
+    where
+        I: IntoIterator<Item = A>,
+        I::IntoIter: Send + 'static,
+    {
+        let (send, inputs) = mpsc::sync_channel(bound);
+        let xs = xs.into_iter();
+        let mut threads = vec![thread::spawn(move || {
+            for a in xs {
+                if send.send(a).is_err() {
+                    break;
+                }
+            }
+        })];
+        let outputs = (self.spawn)(inputs, bound, &mut threads);
+        PipelineRun { outputs, threads }
+    }
+}
+
+/// The outputs of a running pipeline, in the order of their inputs.
+///
+/// Dropping it early stops every stage after the input it is on. If a stage
+/// panics, the panic is raised again here once the outputs before it have
+/// been read.
+pub struct PipelineRun<B> {
+    outputs: Receiver<B>,
+    threads: Threads,
+}
+
+impl<B> Iterator for PipelineRun<B> {
+    type Item = B;
+
+    fn next(&mut self) -> Option<B> {
+        match self.outputs.recv() {
+            Ok(b) => Some(b),
+            Err(_) => {
+                for thread in self.threads.drain(..) {
+                    if let Err(payload) = thread.join() {
+                        panic::resume_unwind(payload);
+                    }
+                }
+                None
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::arrow::*;
+    use crate::impls::circuit::Circuit;
+    use rand::{thread_rng, Rng};
+
+    fn scale() -> SendCircuit<'static, i64, i64> {
+        SendCircuit::arrow(|x| x * 3 - 1)
+    }
+
+    fn running_sum() -> SendCircuit<'static, i64, i64> {
+        SendCircuit::accum(0, |x, total| (total + x, total + x))
+    }
+
+    fn delta() -> SendCircuit<'static, i64, (i64, i64)> {
+        SendCircuit::accum(0, |x, prev| ((x, x - prev), x))
+    }
+
+    #[test]
+    fn test_pipeline() {
+        let mut rng = thread_rng();
+        for bound in [0, 1, 4] {
+            let len = rng.gen_range(0..200);
+            let xs: Vec<i64> = (0..len).map(|_| rng.gen_range(-100..100)).collect();
+            let sequential = Circuit::from(scale().then(running_sum()).then(delta()));
+            let expected: Vec<_> = sequential.run(xs.clone()).collect();
+            let res: Vec<_> = Pipeline::new(scale())
+                .then(running_sum())
+                .then(delta())
+                .run(bound, xs)
+                .collect();
+            assert_eq!(res, expected);
+        }
+    }
+
+    #[test]
+    fn test_pipelined() {
+        let mut rng = thread_rng();
+        let xs: Vec<i64> = (0..200).map(|_| rng.gen_range(-100..100)).collect();
+        let sequential = Circuit::from(scale().then(running_sum()).then(delta()));
+        let expected: Vec<_> = sequential.run(xs.clone()).collect();
+
+        // piped, it still steps in place like `then`
+        let mut piped = scale().pipe(running_sum()).pipe(delta());
+        assert_eq!(piped.run_collect(xs.clone()), expected);
+        for bound in [0, 1, 4] {
+            let piped = scale().pipe(running_sum()).pipe(delta());
+            let res: Vec<_> = piped.pipelined().run(bound, xs.clone()).collect();
+            assert_eq!(res, expected);
+        }
+
+        // the pipe inside `fst` stays on the thread of the stages around it
+        let pairs: Vec<_> = xs.iter().map(|&x| (x, x % 7)).collect();
+        let sequential = Circuit::from(scale().then(running_sum()).fst().then(delta().fst()));
+        let expected: Vec<_> = sequential.run(pairs.clone()).collect();
+        let wrapped = scale().pipe(running_sum()).fst().pipe(delta().fst());
+        let res: Vec<_> = Pipeline::new(wrapped)
+            .then(SendCircuit::id())
+            .run(2, pairs)
+            .collect();
+        assert_eq!(res, expected);
+
+        // one thread for each of a long chain of pipes
+        let chain = (0..64).fold(SendCircuit::id(), |circ, _| circ.pipe(running_sum()));
+        let res: Vec<_> = chain.pipelined().run(1, 0..5).collect();
+        let mut sequential = (0..64).fold(SendCircuit::id(), |circ, _| circ.then(running_sum()));
+        assert_eq!(res, sequential.run_collect(0..5));
+    }
+
+    #[test]
+    fn test_pipelined_short_inputs() {
+        // the threads may be done before the pipeline is built
+        for len in 0..4 {
+            for bound in [0, 1, 4] {
+                let xs: Vec<i64> = (0..len).collect();
+                let mut sequential = scale().then(running_sum()).then(delta());
+                let expected = sequential.run_collect(xs.clone());
+                let piped = scale().pipe(running_sum()).pipe(delta());
+                let res: Vec<_> = piped.pipelined().run(bound, xs).collect();
+                assert_eq!(res, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_pipeline_stops_early() {
+        // the inputs never end, so the stages have to stop when dropped
+        let firsts: Vec<_> = Pipeline::new(running_sum())
+            .then(scale())
+            .run(2, 1..)
+            .take(3)
+            .collect();
+        assert_eq!(firsts, vec![2, 8, 17]);
+    }
+
+    #[test]
+    #[should_panic(expected = "negative input")]
+    fn test_pipeline_panics() {
+        let check = SendCircuit::arrow(|x: i64| {
+            assert!(x >= 0, "negative input");
+            x
+        });
+        let mut run = scale().pipe(check).pipelined().run(1, [1, 2, -5, 3]);
+        assert_eq!(run.next(), Some(2));
+        assert_eq!(run.next(), Some(5));
+        run.next();
+    }
+}
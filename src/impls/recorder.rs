@@ -0,0 +1,250 @@
+//! Stepping a circuit backwards, for debugging.
+//!
+//! A `Recorder` keeps the input and output of each of the last steps of a
+//! `CloneCircuit`, with a copy of the circuit from before the step, so it can
+//! go back to any of them, run them again, or compare an edited circuit
+//! against the recorded steps.
+
+use crate::impls::{circuit::Circuit, clone_circuit::CloneCircuit};
+use alloc::{collections::VecDeque, vec::Vec};
+
+struct Entry<'a, A, B> {
+    before: CloneCircuit<'a, A, B>,
+    input: A,
+    output: B,
+}
+
+/// A circuit that remembers its last `capacity` steps.
+pub struct Recorder<'a, A, B> {
+    circuit: CloneCircuit<'a, A, B>,
+    history: VecDeque<Entry<'a, A, B>>,
+    capacity: usize,
+    // the number of the oldest step in the history, counting from 0
+    first: usize,
+}
+
+/// A step where two circuits gave different outputs for the same input.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Difference<B> {
+    pub step: usize,
+    pub left: B,
+    pub right: B,
+}
+
+impl<'a, A: Clone + 'a, B: Clone + 'a> Recorder<'a, A, B> {
+    pub fn new(circuit: CloneCircuit<'a, A, B>, capacity: usize) -> Self {
+        Self {
+            circuit,
+            history: VecDeque::with_capacity(capacity),
+            capacity,
+            first: 0,
+        }
+    }
+
+    /// Steps the circuit and records the step, forgetting the oldest one if
+    /// the history is full.
+    pub fn step(&mut self, a: A) -> B {
+        if self.capacity == 0 {
+            self.first += 1;
+            return self.circuit.step(a);
+        }
+        let before = self.circuit.fork();
+        let output = self.circuit.step(a.clone());
+        if self.history.len() == self.capacity {
+            self.history.pop_front();
+            self.first += 1;
+        }
+        self.history.push_back(Entry {
+            before,
+            input: a,
+            output: output.clone(),
+        });
+        output
+    }
+
+    /// The number of the next step, i.e. how many steps were taken.
+    pub fn position(&self) -> usize {
+        self.first + self.history.len()
+    }
+
+    /// The recorded steps, oldest first, as their number, input and output.
+    pub fn history(&self) -> impl Iterator<Item = (usize, &A, &B)> + use<'_, 'a, A, B> {
+        let first = self.first;
+        let entries = self.history.iter().enumerate();
+        entries.map(move |(i, entry)| (first + i, &entry.input, &entry.output))
+    }
+
+    /// Undoes the last `n` steps, or as many as were recorded, putting the
+    /// circuit back in the state it had before them. Returns how many steps
+    /// were undone.
+    pub fn rewind(&mut self, n: usize) -> usize {
+        let n = n.min(self.history.len());
+        if let Some(entry) = self.history.drain(self.history.len() - n..).next() {
+            self.circuit = entry.before;
+        }
+        n
+    }
+
+    /// Runs the recorded inputs again from step `k`, starting from a copy of
+    /// the circuit as it was before that step. The recorder is unchanged.
+    /// `None` if step `k` is not in the history.
+    pub fn replay_from(&self, k: usize) -> Option<Vec<B>> {
+        let start = k.checked_sub(self.first)?;
+        let mut circuit = self.history.get(start)?.before.fork();
+        let inputs = self.history.range(start..).map(|entry| entry.input.clone());
+        Some(circuit.run_collect(inputs))
+    }
+
+    /// Feeds the recorded inputs through two new circuits, e.g. the recorded
+    /// one before and after an edit, and returns the steps where their
+    /// outputs differ. `None` if the oldest steps were forgotten, as the
+    /// circuits would then start from a different state than the recorded
+    /// one did.
+    pub fn diff<C: PartialEq>(
+        &self,
+        mut left: Circuit<'a, A, C>,
+        mut right: Circuit<'a, A, C>,
+    ) -> Option<Vec<Difference<C>>> {
+        self.differences(|entry| {
+            (
+                left.step(entry.input.clone()),
+                right.step(entry.input.clone()),
+            )
+        })
+    }
+
+    /// Feeds the recorded inputs through a new circuit, e.g. an edited copy
+    /// of the recorded one, and returns the steps where its output differs
+    /// from the recorded output, which is on the left. `None` if the oldest
+    /// steps were forgotten, as for `diff`.
+    pub fn check(&self, mut edited: Circuit<'a, A, B>) -> Option<Vec<Difference<B>>>
+    where
+        B: PartialEq,
+    {
+        self.differences(|entry| (entry.output.clone(), edited.step(entry.input.clone())))
+    }
+
+    fn differences<C, F>(&self, mut outputs: F) -> Option<Vec<Difference<C>>>
+    where
+        C: PartialEq,
+        F: FnMut(&Entry<'a, A, B>) -> (C, C),
+    {
+        if self.first > 0 {
+            return None;
+        }
+        let diffs = self.history.iter().enumerate().filter_map(|(step, entry)| {
+            let (left, right) = outputs(entry);
+            (left != right).then_some(Difference { step, left, right })
+        });
+        Some(diffs.collect())
+    }
+
+    /// The circuit in its current state.
+    pub fn circuit(&self) -> &CloneCircuit<'a, A, B> {
+        &self.circuit
+    }
+
+    pub fn into_circuit(self) -> CloneCircuit<'a, A, B> {
+        self.circuit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the mean of the last four inputs, which goes wrong on an input of 0,
+    // as it resets the window
+    fn moving_mean(buggy: bool) -> CloneCircuit<'static, u32, u32> {
+        CloneCircuit::accum(Vec::new(), move |x: u32, mut window: Vec<u32>| {
+            if buggy && x == 0 {
+                window.clear();
+            }
+            window.push(x);
+            if window.len() > 4 {
+                window.remove(0);
+            }
+            let mean = window.iter().sum::<u32>() / window.len() as u32;
+            (mean, window)
+        })
+    }
+
+    fn input(i: u32) -> u32 {
+        // a 0 now and then, at sample 9,997 among others
+        if i % 1000 == 997 {
+            0
+        } else {
+            i % 50 + 1
+        }
+    }
+
+    #[test]
+    fn test_rewind() {
+        let mut recorder = Recorder::new(moving_mean(true), 100);
+        let outputs: Vec<_> = (0..10_000).map(|i| recorder.step(input(i))).collect();
+        assert_eq!(recorder.position(), 10_000);
+        assert_eq!(recorder.history().count(), 100);
+        assert_eq!(recorder.history().next().map(|(k, _, _)| k), Some(9_900));
+
+        // going back and stepping again gives the same outputs
+        assert_eq!(recorder.rewind(5), 5);
+        assert_eq!(recorder.position(), 9_995);
+        let again: Vec<_> = (9_995..10_000).map(|i| recorder.step(input(i))).collect();
+        assert_eq!(again, outputs[9_995..]);
+
+        assert_eq!(recorder.replay_from(9_990), Some(outputs[9_990..].to_vec()));
+        assert_eq!(recorder.replay_from(9_000), None);
+        assert_eq!(recorder.replay_from(10_000), None);
+
+        assert_eq!(recorder.rewind(1_000), 100);
+        assert_eq!(recorder.position(), 9_900);
+        assert_eq!(
+            recorder.circuit().fork().output(input(9_900)),
+            outputs[9_900]
+        );
+
+        // with no history it only steps
+        let mut unrecorded = Recorder::new(moving_mean(true), 0);
+        let res: Vec<_> = (0..100).map(|i| unrecorded.step(input(i))).collect();
+        assert_eq!(res, outputs[..100]);
+        assert_eq!(unrecorded.position(), 100);
+        assert_eq!(unrecorded.rewind(1), 0);
+    }
+
+    #[test]
+    fn test_diff() {
+        let mut recorder = Recorder::new(moving_mean(true), 10_000);
+        for i in 0..10_000 {
+            recorder.step(input(i));
+        }
+
+        // the fixed circuit only differs from the 0 at sample 997 on, for
+        // three steps after each 0
+        let diffs = recorder.diff(moving_mean(true).into(), moving_mean(false).into());
+        let steps: Vec<_> = diffs.unwrap().iter().map(|d| d.step).collect();
+        assert_eq!(steps.len(), 30);
+        assert_eq!(steps[27..], [9_997, 9_998, 9_999]);
+
+        let checked = recorder.check(moving_mean(false).into()).unwrap();
+        assert_eq!(checked.len(), 30);
+        assert_eq!(
+            checked[27],
+            Difference {
+                step: 9_997,
+                left: 0,
+                right: (input(9_994) + input(9_995) + input(9_996)) / 4,
+            }
+        );
+        assert_eq!(recorder.check(moving_mean(true).into()), Some(vec![]));
+
+        // a circuit built anew can't be compared with the last steps alone
+        let mut recent = Recorder::new(moving_mean(true), 100);
+        for i in 0..10_000 {
+            recent.step(input(i));
+        }
+        assert_eq!(recent.check(moving_mean(false).into()), None);
+        assert!(recent
+            .diff(moving_mean(false).into(), moving_mean(false).into())
+            .is_none());
+    }
+}
@@ -0,0 +1,606 @@
+//! Circuits that can be moved to another thread.
+//!
+//! `Circuit` accepts any closure, so it can't be `Send`. A `SendCircuit`
+//! only stores `Send` closures and state: its family only `Holds` `Send`
+//! values, so the arrow traits take `Send` functions and `loop_d` takes a
+//! `Send` initial value. A `SendCircuit` converts into a `Circuit` once it
+//! is on the thread that runs it.
+//!
+//! `pipe` composes two circuits like `then`, and marks where `pipelined`
+//! splits the circuit between threads. `then` can't do both, as the value
+//! passed from one thread to the next has to be `Send`.
+
+#[cfg(feature = "std")]
+use crate::impls::{
+    either::Either,
+    pipeline::Pipeline,
+    stages::{append, expect_output, outside_wraps, run_stages, Entry},
+};
+use crate::{
+    arrow::*,
+    impls::{
+        circuit::Circuit,
+        stages::{circuit_impls, Repr},
+    },
+};
+use alloc::{boxed::Box, vec::Vec};
+#[cfg(feature = "std")]
+use std::{
+    collections::VecDeque,
+    mem,
+    num::NonZeroUsize,
+    panic,
+    sync::mpsc::{self, Receiver, RecvError, SendError, Sender},
+    thread::{self, JoinHandle, ScopedJoinHandle},
+    time::{Duration, Instant},
+};
+
+pub struct SendCircuitFamily;
+
+type Step<'a, A, B> = Box<dyn FnMut(A) -> B + Send + 'a>;
+
+// What `pipelined` splits a circuit with, between the stages `pipe` joined:
+// given the bound of the channel between the two threads, it makes the end
+// that sends what the producer left and the end that receives it for the
+// consumer.
+type Link<'a> = Box<dyn FnOnce(usize) -> (Handoff<'a>, Handoff<'a>) + Send + 'a>;
+
+// Moves one value between a slot and a channel, or fails once the thread at
+// the other end has stopped.
+type Handoff<'a> = Box<dyn FnMut() -> bool + Send + 'a>;
+
+pub struct SendCircuit<'a, A, B> {
+    repr: Repr<Step<'a, A, B>, Step<'a, bool, ()>, Link<'a>, A, B>,
+}
+
+circuit_impls!(SendCircuit, SendCircuitFamily, Send);
+
+impl<'a, A: 'a> SendCircuit<'a, A, A> {
+    // id :: cat a a
+    pub fn id() -> Self {
+        SendCircuitFamily::id()
+    }
+}
+
+impl<'a, A: 'a, B: 'a> From<SendCircuit<'a, A, B>> for Circuit<'a, A, B> {
+    fn from(mut circuit: SendCircuit<'a, A, B>) -> Self {
+        Circuit::from_step(move |a| circuit.step(a))
+    }
+}
+
+#[cfg(feature = "std")]
+impl<'a, A: 'a, B: Send + 'a> SendCircuit<'a, A, B> {
+    /// Like `then`, where `pipelined` runs `consumer` on a thread after the
+    /// one that runs this circuit, unless both are inside an `fst`, `left`
+    /// or `loop_d`.
+    // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
+    pub fn pipe<C: 'a>(self, consumer: SendCircuit<'a, B, C>) -> SendCircuit<'a, A, C> {
+        let (input, stages, mid) = self.into_flat();
+        let (consumer_input, consumer_stages, output) = consumer.into_flat();
+        let (from, to) = (mid.clone(), consumer_input.clone());
+        let connect: Step<'a, bool, ()> = Box::new(move |_: bool| consumer_input.set(mid.take()));
+        let link: Link<'a> = Box::new(move |bound| {
+            let (send, recv) = mpsc::sync_channel(bound);
+            let push: Handoff<'a> = Box::new(move || send.send(expect_output(from.take())).is_ok());
+            let pull: Handoff<'a> = Box::new(move || recv.recv().map(|b| to.set(Some(b))).is_ok());
+            (push, pull)
+        });
+        SendCircuit {
+            repr: Repr::Flat {
+                input,
+                stages: append(stages, Entry::Link(connect, link), consumer_stages),
+                output,
+            },
+        }
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A, B> SendCircuit<'static, A, B>
+where
+    A: Send + 'static,
+    B: Send + 'static,
+{
+    /// A pipeline that runs the circuit split where it was composed with
+    /// `pipe`, each part on its own thread.
+    pub fn pipelined(self) -> Pipeline<A, B> {
+        Pipeline::new(self)
+    }
+
+    // Starts a thread for each part of the circuit between two links, fed
+    // from `inputs`, and returns the outputs of the last one.
+    pub(crate) fn spawn_parts(
+        self,
+        inputs: Receiver<A>,
+        bound: usize,
+        threads: &mut Vec<JoinHandle<()>>,
+    ) -> Receiver<B> {
+        let (input, stages, output) = self.into_flat();
+        let (send, outputs) = mpsc::sync_channel(bound);
+        let mut pull: Handoff<'static> =
+            Box::new(move || inputs.recv().map(|a| input.set(Some(a))).is_ok());
+        let last: Handoff<'static> =
+            Box::new(move || send.send(expect_output(output.take())).is_ok());
+        // a link inside a wrapped circuit stays a stage, as the stages
+        // around it share what they keep
+        let outside = outside_wraps(&stages);
+        let mut part = VecDeque::new();
+        for (entry, outside) in stages.into_iter().zip(outside) {
+            match entry {
+                Entry::Link(connect, link) if outside => {
+                    // the stage shares the slots on both sides of the link, so
+                    // it is dropped before either side moves to its thread
+                    drop(connect);
+                    let (push, next) = link(bound);
+                    threads.push(spawn_part(pull, mem::take(&mut part), push));
+                    pull = next;
+                }
+                Entry::Link(stage, _) => part.push_back(Entry::Stage(stage)),
+                entry => part.push_back(entry),
+            }
+        }
+        threads.push(spawn_part(pull, part, last));
+        outputs
+    }
+}
+
+#[cfg(feature = "std")]
+fn spawn_part(
+    mut pull: Handoff<'static>,
+    mut stages: VecDeque<Entry<Step<'static, bool, ()>, Link<'static>>>,
+    mut push: Handoff<'static>,
+) -> JoinHandle<()> {
+    // stops early once the threads after it have stopped
+    thread::spawn(move || {
+        while pull() {
+            run_stages(&mut stages);
+            if !push() {
+                break;
+            }
+        }
+    })
+}
+
+/// How the `par_` combinators split their branches across threads.
+#[cfg(feature = "std")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Parallelism {
+    /// The most threads a step runs on, counting the one that steps.
+    pub threads: usize,
+    /// The branches for another thread are stepped on the one that steps
+    /// while their last step took less than this, as starting a thread costs
+    /// more than a cheap step.
+    pub min_work: Duration,
+}
+
+#[cfg(feature = "std")]
+impl Default for Parallelism {
+    /// As many threads as the machine runs at once, for branches that take
+    /// at least 50µs a step.
+    fn default() -> Self {
+        Self {
+            threads: thread::available_parallelism().map_or(1, NonZeroUsize::get),
+            min_work: Duration::from_micros(50),
+        }
+    }
+}
+
+// A circuit for another thread, with whether its last step was slow enough
+// to be worth starting a thread for the next one.
+#[cfg(feature = "std")]
+struct Branch<'a, A, B> {
+    circuit: SendCircuit<'a, A, B>,
+    slow: bool,
+    min_work: Duration,
+}
+
+#[cfg(feature = "std")]
+impl<'a, A: 'a, B: 'a> Branch<'a, A, B> {
+    fn new(circuit: SendCircuit<'a, A, B>, par: Parallelism) -> Self {
+        Self {
+            circuit,
+            slow: par.min_work.is_zero(),
+            min_work: par.min_work,
+        }
+    }
+
+    fn step(&mut self, a: A) -> B {
+        let started = Instant::now();
+        let b = self.circuit.step(a);
+        self.slow = started.elapsed() >= self.min_work;
+        b
+    }
+}
+
+#[cfg(feature = "std")]
+fn join<T>(handle: ScopedJoinHandle<'_, T>) -> T {
+    handle
+        .join()
+        .unwrap_or_else(|payload| panic::resume_unwind(payload))
+}
+
+// A slow branch is stepped on a scoped thread, which only lives for the step,
+// so the branches may borrow and they keep their state in the circuit between
+// steps. The threads are started again every step rather than kept, as a
+// thread that outlives the step would need `'static` branches.
+#[cfg(feature = "std")]
+impl<'a, A: Send + 'a, B: Send + 'a> SendCircuit<'a, A, B> {
+    /// Like `both`, stepping `g` on another thread at the same time when its
+    /// steps are worth it.
+    // (***) :: m b c -> m b' c' -> m (b,b') (c,c')
+    pub fn par_both<A1, B1>(
+        mut self,
+        g: SendCircuit<'a, A1, B1>,
+        par: Parallelism,
+    ) -> SendCircuit<'a, (A, A1), (B, B1)>
+    where
+        A1: Send + 'a,
+        B1: Send + 'a,
+    {
+        if par.threads < 2 {
+            return self.both(g);
+        }
+        let mut g = Branch::new(g, par);
+        SendCircuit::from_step(move |(a, a1)| {
+            if !g.slow {
+                return (self.step(a), g.step(a1));
+            }
+            thread::scope(|scope| {
+                let other = scope.spawn(|| g.step(a1));
+                (self.step(a), join(other))
+            })
+        })
+    }
+
+    /// Like `dup`, stepping `g` on another thread at the same time when its
+    /// steps are worth it.
+    // (&&&) :: m a b -> m a b' -> m a (b,b')
+    pub fn par_dup<B1>(
+        self,
+        g: SendCircuit<'a, A, B1>,
+        par: Parallelism,
+    ) -> SendCircuit<'a, A, (B, B1)>
+    where
+        A: Clone,
+        B1: Send + 'a,
+    {
+        self.par_both(g, par).after_pure(|a: A| (a.clone(), a))
+    }
+
+    /// Steps every branch on the same input and collects their outputs in
+    /// order. The branches are split evenly between the threads, and the
+    /// ones for other threads are stepped there when their steps are worth
+    /// it.
+    pub fn par_fanout(
+        branches: Vec<SendCircuit<'a, A, B>>,
+        par: Parallelism,
+    ) -> SendCircuit<'a, A, Vec<B>>
+    where
+        A: Clone,
+    {
+        let per_thread = branches.len().div_ceil(par.threads.max(1)).max(1);
+        let mut chunks = Vec::new();
+        let mut branches = branches.into_iter().peekable();
+        while branches.peek().is_some() {
+            let mut chunk: Vec<_> = branches.by_ref().take(per_thread).collect();
+            chunks.push(SendCircuit::from_step(move |a: A| {
+                chunk
+                    .iter_mut()
+                    .map(|f| f.step(a.clone()))
+                    .collect::<Vec<_>>()
+            }));
+        }
+        let mut chunks = chunks.into_iter();
+        let mut here = chunks.next();
+        let mut others: Vec<_> = chunks.map(|chunk| Branch::new(chunk, par)).collect();
+        SendCircuit::from_step(move |a: A| {
+            thread::scope(|scope| {
+                let started: Vec<_> = others
+                    .iter_mut()
+                    .map(|other| match other.slow {
+                        true => {
+                            let a = a.clone();
+                            Either::Left(scope.spawn(move || other.step(a)))
+                        }
+                        false => Either::Right(other),
+                    })
+                    .collect();
+                let mut outputs = here.as_mut().map_or_else(Vec::new, |f| f.step(a.clone()));
+                for other in started {
+                    outputs.extend(match other {
+                        Either::Left(handle) => join(handle),
+                        Either::Right(other) => other.step(a.clone()),
+                    });
+                }
+                outputs
+            })
+        })
+    }
+}
+
+#[cfg(feature = "std")]
+impl<A, B> SendCircuit<'static, A, B>
+where
+    A: Send + 'static,
+    B: Send + 'static,
+{
+    /// Moves the circuit to a new thread, which steps it once for every
+    /// input sent to it and sends back each output in order.
+    pub fn spawn(mut self) -> CircuitThread<A, B> {
+        let (inputs, worker_inputs) = mpsc::channel();
+        let (worker_outputs, outputs) = mpsc::channel();
+        let handle = thread::spawn(move || {
+            for a in worker_inputs {
+                if worker_outputs.send(self.step(a)).is_err() {
+                    break;
+                }
+            }
+            self
+        });
+        CircuitThread {
+            inputs,
+            outputs,
+            handle,
+        }
+    }
+}
+
+/// A circuit running on its own thread, fed through channels.
+#[cfg(feature = "std")]
+pub struct CircuitThread<A, B> {
+    inputs: Sender<A>,
+    outputs: Receiver<B>,
+    handle: JoinHandle<SendCircuit<'static, A, B>>,
+}
+
+#[cfg(feature = "std")]
+impl<A, B> CircuitThread<A, B> {
+    /// Queues an input without waiting for its output. Fails if the thread
+    /// has stopped, e.g. because the circuit panicked.
+    pub fn send(&self, a: A) -> Result<(), SendError<A>> {
+        self.inputs.send(a)
+    }
+
+    /// Waits for the output of the oldest input not yet received.
+    pub fn recv(&self) -> Result<B, RecvError> {
+        self.outputs.recv()
+    }
+
+    /// The outputs already produced, without waiting for more.
+    pub fn try_iter(&self) -> mpsc::TryIter<'_, B> {
+        self.outputs.try_iter()
+    }
+
+    /// Stops the thread once it has stepped every input sent so far, and
+    /// returns the circuit in its final state. Outputs that were not
+    /// received are dropped.
+    pub fn join(self) -> thread::Result<SendCircuit<'static, A, B>> {
+        drop(self.inputs);
+        self.handle.join()
+    }
+}
+
+#[cfg(all(test, feature = "std"))]
+mod tests {
+    use super::*;
+    use crate::impls::either::Either;
+
+    use rand::{rngs::StdRng, Rng, SeedableRng};
+
+    fn assert_send<T: Send>(_: &T) {}
+
+    #[test]
+    fn test_send_circuit() {
+        let mut running = SendCircuit::accum(0, |n: i32, total| (total + n, total + n))
+            .dup(SendCircuit::arrow(|n: i32| n * 2))
+            .then_pure(|(total, double)| total - double);
+        assert_send(&running);
+        assert_eq!(running.run_collect([1, 2, 3]), vec![-1, -1, 0]);
+
+        let parity = SendCircuit::arrow(|n: u32| Either::from((n.is_multiple_of(2), n)))
+            .then(SendCircuit::arrow(|n| n * 3).owise(SendCircuit::id()));
+        let mut tagged = parity
+            .fst()
+            .then(SendCircuit::id().both(SendCircuit::arrow(str::len)));
+        assert_eq!(tagged.step((4, "ab")), (4, 2));
+        assert_eq!(tagged.step((5, "abc")), (15, 3));
+
+        let sum = SendCircuit::arrow(|(n, total): (i32, i32)| (n + total, n + total)).loop_d(0);
+        // converted, it composes with the other circuits
+        let mut shown =
+            Circuit::from(sum).then(crate::impls::circuit::arrow(|n: i32| n.to_string()));
+        assert_eq!(shown.run_collect([1, 2, 3]), vec!["1", "3", "6"]);
+    }
+
+    #[test]
+    fn test_generic() {
+        let parity = cond(
+            |n: &usize| n.is_multiple_of(2),
+            SendCircuit::arrow(|n| format!("{} is even", n)),
+            SendCircuit::arrow(|n| format!("{} is odd", n)),
+        );
+        assert_send(&parity);
+        let mut parity = Circuit::from(parity);
+        assert_eq!(parity.run_collect([1, 2]), vec!["1 is odd", "2 is even"]);
+
+        let total = SendCircuit::accum_dup(0, |n: usize, total| n + total);
+        let mut counted = left_with::<_, _, _, &str, _>(total);
+        let res = counted.run_collect([
+            (Either::Left(2), 'a'),
+            (Either::Right("skip"), 'b'),
+            (Either::Left(3), 'c'),
+        ]);
+        assert_eq!(
+            res,
+            vec![
+                (Either::Left(2), 'a'),
+                (Either::Right("skip"), 'b'),
+                (Either::Left(5), 'c')
+            ]
+        );
+
+        let stats = crate::proc!(n: usize => {
+            sum <- SendCircuit::accum_dup(0, |n: usize, sum| n + sum) -< n * 2;
+            count <- SendCircuit::accum_dup(0, |n: usize, count| n + count) -< 1;
+            return_a -< (n, sum / count)
+        });
+        assert_send(&stats);
+        let worker = stats.spawn();
+        for n in 1..=3 {
+            worker.send(n).unwrap();
+        }
+        let res: Vec<_> = (0..3).map(|_| worker.recv().unwrap()).collect();
+        assert_eq!(res, vec![(1, 2), (2, 3), (3, 4)]);
+    }
+
+    #[test]
+    fn test_long_composition() {
+        // long enough to be flat, so it moves its stages to the thread
+        let counted = (0..100_000).fold(SendCircuit::id(), |circ, _| {
+            circ.fst()
+                .then_pure(|(n, count): (u64, u64)| (n + count, count + 1))
+                .loop_d(0)
+        });
+        let worker = counted.spawn();
+        for _ in 0..3 {
+            worker.send(0).unwrap();
+        }
+        let res: Vec<_> = (0..3).map(|_| worker.recv().unwrap()).collect();
+        assert_eq!(res, vec![0, 100_000, 200_000]);
+
+        let mut circuit = worker.join().unwrap();
+        assert_eq!(circuit.step(1), 300_001);
+    }
+
+    #[test]
+    fn test_spawn() {
+        let worker = SendCircuit::accum(0, |n: u64, total| (total + n, total + n)).spawn();
+        for n in 1..=4 {
+            worker.send(n).unwrap();
+        }
+        let totals: Vec<_> = (0..4).map(|_| worker.recv().unwrap()).collect();
+        assert_eq!(totals, vec![1, 3, 6, 10]);
+
+        // the state survives the trip back from the thread
+        let mut circuit = worker.join().unwrap();
+        assert_eq!(circuit.step(5), 15);
+
+        let worker = SendCircuit::from_step(|n: u64| {
+            assert!(n < 2, "input out of range");
+            n
+        })
+        .spawn();
+        worker.send(5).unwrap();
+        assert!(worker.recv().is_err());
+        assert!(worker.join().is_err());
+    }
+
+    // a branch that depends on all of its earlier inputs, so the outputs
+    // only match if every branch sees every input once, in order
+    fn branch(k: i64) -> SendCircuit<'static, i64, i64> {
+        SendCircuit::accum(k, move |x, acc| (acc * 31 % 1009 + x * k, acc + x))
+    }
+
+    #[test]
+    fn test_parallel() {
+        let mut rng = StdRng::seed_from_u64(47);
+        let xs: Vec<i64> = (0..100).map(|_| rng.gen_range(-100..100)).collect();
+        let pars = [
+            Parallelism::default(),
+            Parallelism {
+                threads: 1,
+                min_work: Duration::ZERO,
+            },
+            // every step of a branch for another thread starts one
+            Parallelism {
+                threads: 3,
+                min_work: Duration::ZERO,
+            },
+            // and none ever does
+            Parallelism {
+                threads: 4,
+                min_work: Duration::from_secs(3600),
+            },
+        ];
+        for par in pars {
+            let pairs: Vec<_> = xs.iter().map(|&x| (x, x * 2)).collect();
+            let expected = branch(1).both(branch(2)).run_collect(pairs.clone());
+            let res = branch(1).par_both(branch(2), par).run_collect(pairs);
+            assert_eq!(res, expected);
+
+            let expected = branch(3).dup(branch(4)).run_collect(xs.clone());
+            let res = branch(3).par_dup(branch(4), par).run_collect(xs.clone());
+            assert_eq!(res, expected);
+
+            let mut sequential: Vec<_> = (1..=7).map(branch).collect();
+            let expected: Vec<Vec<_>> = xs
+                .iter()
+                .map(|&x| sequential.iter_mut().map(|f| f.step(x)).collect())
+                .collect();
+            let mut fanout = SendCircuit::par_fanout((1..=7).map(branch).collect(), par);
+            assert_eq!(fanout.run_collect(xs.clone()), expected);
+        }
+        assert!(SendCircuit::par_fanout(
+            Vec::<SendCircuit<i64, i64>>::new(),
+            Parallelism::default()
+        )
+        .output(1)
+        .is_empty());
+    }
+
+    #[test]
+    fn test_parallel_moves_slow_branches() {
+        // slow from the tenth step on, when it moves to another thread with
+        // the state it has built up
+        let slow = |k| {
+            let delay = SendCircuit::accum(0, |x, steps| {
+                if steps >= 10 {
+                    thread::sleep(Duration::from_millis(2));
+                }
+                (x, steps + 1)
+            });
+            delay.then(branch(k))
+        };
+        let par = Parallelism {
+            threads: 2,
+            min_work: Duration::from_millis(1),
+        };
+        let xs: Vec<i64> = (0..20).collect();
+        let expected = branch(1).dup(branch(2)).run_collect(xs.clone());
+        let res = branch(1).par_dup(slow(2), par).run_collect(xs);
+        assert_eq!(res, expected);
+    }
+
+    #[test]
+    fn test_parallel_borrows() {
+        // the threads only live for a step, so the branches may borrow
+        let weights = vec![2, 3, 5];
+        let weights = &weights;
+        let weighted = |i: usize| SendCircuit::from_step(move |x: i64| x * weights[i]);
+        let par = Parallelism {
+            threads: 3,
+            min_work: Duration::ZERO,
+        };
+        let mut fanout = SendCircuit::par_fanout((0..3).map(weighted).collect(), par);
+        assert_eq!(
+            fanout.run_collect(vec![1, 2]),
+            vec![vec![2, 3, 5], vec![4, 6, 10]]
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "input out of range")]
+    fn test_parallel_panics() {
+        let check = SendCircuit::from_step(|n: u64| {
+            assert!(n < 2, "input out of range");
+            n
+        });
+        let par = Parallelism {
+            threads: 2,
+            min_work: Duration::ZERO,
+        };
+        let mut both = SendCircuit::id().par_both(check, par);
+        assert_eq!(both.step((0, 1)), (0, 1));
+        both.step((0, 5));
+    }
+}
@@ -0,0 +1,570 @@
+//! The representation shared by `Circuit`, `SendCircuit` and `CloneCircuit`.
+//!
+//! The three only differ in the bound on the closures they box: none, `Send`
+//! or `Clone`. Rust can't abstract over such a bound, so `circuit_impls!`
+//! writes the same combinators out for each of them, and the parts that
+//! don't box anything are generic here. Only a `SendCircuit` has stages that
+//! can be split between threads, where `pipe` links them.
+
+use crate::impls::either::Either;
+use alloc::{collections::VecDeque, rc::Rc};
+use core::{cell::Cell, mem};
+
+// A circuit owns the state of every stage inside it and updates that state
+// in place, so composing allocates once when the circuit is built and
+// stepping allocates nothing.
+//
+// Short compositions are closures calling into each other, which is the
+// fastest to step. Past `MAX_NESTED` calls deep, `then` switches to a flat
+// list of stages that pass values through shared slots instead, and `fst`,
+// `left` and `loop_d` add a stage before and after the ones they wrap, so
+// stepping and dropping a long pipeline doesn't recurse once per stage.
+pub(crate) const MAX_NESTED: usize = 32;
+
+pub(crate) enum Repr<S, T, L, A, B> {
+    Nested {
+        step: S,
+        depth: usize,
+    },
+    Flat {
+        input: Slot<A>,
+        stages: VecDeque<Entry<T, L>>,
+        output: Slot<B>,
+    },
+    // a flat circuit whose step panicked
+    Poisoned,
+}
+
+// The copies share their slots, which are empty between steps. A stage of
+// one copy stepping another would mix up the values in them.
+impl<S: Clone, T: Clone, L: Clone, A, B> Clone for Repr<S, T, L, A, B> {
+    fn clone(&self) -> Self {
+        match self {
+            Self::Nested { step, depth } => Self::Nested {
+                step: step.clone(),
+                depth: *depth,
+            },
+            Self::Flat {
+                input,
+                stages,
+                output,
+            } => Self::Flat {
+                input: input.clone(),
+                stages: stages.clone(),
+                output: output.clone(),
+            },
+            Self::Poisoned => Self::Poisoned,
+        }
+    }
+}
+
+// A stage is called with `false` in its place in the list, and the ones
+// `fst`, `left` and `loop_d` add before the stages they wrap are called
+// again with `true` from the `Exit` after them, so that what they keep
+// between the two is in one closure.
+#[derive(Clone)]
+pub(crate) enum Entry<T, L> {
+    Stage(T),
+    // the distance back to the stage to call
+    Exit(usize),
+    // a stage joining a producer to a consumer, with the `L` that splits
+    // them between two threads instead, for the circuit types that can
+    #[cfg_attr(not(feature = "std"), allow(dead_code))]
+    Link(T, L),
+}
+
+/// Where one stage leaves its output for the next.
+pub(crate) struct Slot<T>(Rc<Cell<Option<T>>>);
+
+impl<T> Slot<T> {
+    pub(crate) fn new() -> Self {
+        Self(Rc::new(Cell::new(None)))
+    }
+
+    pub(crate) fn set(&self, value: Option<T>) {
+        self.0.set(value)
+    }
+
+    pub(crate) fn take(&self) -> Option<T> {
+        self.0.take()
+    }
+}
+
+impl<T> Clone for Slot<T> {
+    fn clone(&self) -> Self {
+        Self(Rc::clone(&self.0))
+    }
+}
+
+// SAFETY: the copies of a slot are all in the stages of one circuit, and of
+// its clones for a `CloneCircuit`, which is not `Send`. A slot holds a value
+// only from the stage that fills it to the next one, within a step, and a
+// step that panics drops the stages before the panic leaves it. So a
+// `SendCircuit` moving to another thread takes every copy of its slots
+// along, and never a value in them. Splitting one at its `Link`s drops the
+// stage joining the two sides first, the only one with copies on both, and
+// the values crossing between threads go through channels, which need them
+// to be `Send`.
+unsafe impl<T> Send for Slot<T> {}
+
+pub(crate) fn expect_output<B>(b: Option<B>) -> B {
+    b.expect("a stage of the circuit left no output")
+}
+
+// A flat circuit counts as a single call, as its stages run one after the
+// other.
+pub(crate) fn depth<S, T, L, A, B>(repr: &Repr<S, T, L, A, B>) -> usize {
+    match repr {
+        Repr::Nested { depth, .. } => *depth,
+        Repr::Flat { .. } | Repr::Poisoned => 1,
+    }
+}
+
+pub(crate) fn step<S, T, L, A, B>(repr: &mut Repr<S, T, L, A, B>, a: A) -> B
+where
+    S: FnMut(A) -> B,
+    T: FnMut(bool),
+{
+    match repr {
+        Repr::Nested { step, .. } => step(a),
+        Repr::Poisoned => panic!("a step of the circuit panicked"),
+        Repr::Flat { .. } => {
+            // taken out for the step, so that a panic drops the stages and
+            // any values left in their slots
+            let Repr::Flat {
+                input,
+                mut stages,
+                output,
+            } = mem::replace(repr, Repr::Poisoned)
+            else {
+                unreachable!()
+            };
+            input.set(Some(a));
+            run_stages(&mut stages);
+            // each stage fills its output slot from its input slot
+            let b = output.take();
+            *repr = Repr::Flat {
+                input,
+                stages,
+                output,
+            };
+            expect_output(b)
+        }
+    }
+}
+
+pub(crate) fn run_stages<T, L>(stages: &mut VecDeque<Entry<T, L>>)
+where
+    T: FnMut(bool),
+{
+    let list = stages.make_contiguous();
+    for i in 0..list.len() {
+        match &mut list[i] {
+            Entry::Stage(stage) | Entry::Link(stage, _) => stage(false),
+            Entry::Exit(back) => {
+                let back = *back;
+                if let Entry::Stage(stage) = &mut list[i - back] {
+                    stage(true);
+                }
+            }
+        }
+    }
+}
+
+// Whether each entry is outside of every circuit that `fst`, `left` or
+// `loop_d` wrapped, i.e. not between a wrapping stage and its `Exit`.
+#[cfg(feature = "std")]
+pub(crate) fn outside_wraps<T, L>(stages: &VecDeque<Entry<T, L>>) -> alloc::vec::Vec<bool> {
+    let mut first_wrapped = usize::MAX;
+    let mut outside = alloc::vec![false; stages.len()];
+    for (i, entry) in stages.iter().enumerate().rev() {
+        outside[i] = i < first_wrapped;
+        if let Entry::Exit(back) = entry {
+            first_wrapped = first_wrapped.min(i - back);
+        }
+    }
+    outside
+}
+
+// Joins the stages of a producer and a consumer with the entry `connect`
+// between them. The shorter list goes onto the longer one, so a long chain
+// takes linear time to build from either end.
+pub(crate) fn append<T, L>(
+    mut stages: VecDeque<Entry<T, L>>,
+    connect: Entry<T, L>,
+    mut consumer_stages: VecDeque<Entry<T, L>>,
+) -> VecDeque<Entry<T, L>> {
+    if stages.len() >= consumer_stages.len() {
+        stages.push_back(connect);
+        stages.extend(consumer_stages);
+        stages
+    } else {
+        consumer_stages.push_front(connect);
+        for entry in stages.into_iter().rev() {
+            consumer_stages.push_front(entry);
+        }
+        consumer_stages
+    }
+}
+
+/// How `fst`, `left` and `loop_d` run a circuit inside a bigger one: `enter`
+/// picks the part of each input the circuit runs on, if any, and `exit`
+/// puts its output back together with the rest.
+pub(crate) trait Wrapper<A0, A, B, B0> {
+    type Rest;
+
+    fn enter(&mut self, a0: A0) -> (Option<A>, Self::Rest);
+
+    fn exit(&mut self, b: Option<B>, rest: Self::Rest) -> B0;
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Fst;
+
+impl<A, B, C> Wrapper<(A, C), A, B, (B, C)> for Fst {
+    type Rest = C;
+
+    fn enter(&mut self, (a, c): (A, C)) -> (Option<A>, C) {
+        (Some(a), c)
+    }
+
+    fn exit(&mut self, b: Option<B>, c: C) -> (B, C) {
+        (expect_output(b), c)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub(crate) struct Left;
+
+impl<A, B, D> Wrapper<Either<A, D>, A, B, Either<B, D>> for Left {
+    type Rest = Option<D>;
+
+    fn enter(&mut self, either_ad: Either<A, D>) -> (Option<A>, Option<D>) {
+        match either_ad {
+            Either::Left(a) => (Some(a), None),
+            Either::Right(d) => (None, Some(d)),
+        }
+    }
+
+    fn exit(&mut self, b: Option<B>, d: Option<D>) -> Either<B, D> {
+        match d {
+            Some(d) => Either::Right(d),
+            None => Either::Left(expect_output(b)),
+        }
+    }
+}
+
+// The feedback is kept in a register, which is empty while the circuit
+// steps.
+#[derive(Clone)]
+pub(crate) struct LoopD<R>(pub(crate) R);
+
+impl<A, B, D, R> Wrapper<A, (A, D), (B, D), B> for LoopD<R>
+where
+    R: FnMut(Option<D>) -> Option<D>,
+{
+    type Rest = ();
+
+    fn enter(&mut self, a: A) -> (Option<(A, D)>, ()) {
+        let d = (self.0)(None).expect("the register of a loop held no value");
+        (Some((a, d)), ())
+    }
+
+    fn exit(&mut self, bd: Option<(B, D)>, (): ()) -> B {
+        let (b, d) = expect_output(bd);
+        (self.0)(Some(d));
+        b
+    }
+}
+
+/// The arrow instances and the shared methods of a circuit type `$circ`,
+/// with a `repr` field, whose family is `$family`, and whose closures are
+/// boxed as the `Step<'a, A, B>` in scope, with the extra bound `$bound`.
+/// `Link<'a>` in scope is what splits its stages between threads, if they
+/// can be.
+macro_rules! circuit_impls {
+    ($circ:ident, $family:ident $(, $bound:ident)?) => {
+        impl<'a, A: 'a, B: 'a> $crate::arrow::Morphism<'a, A, B> for $circ<'a, A, B> {
+            type Family = $family;
+        }
+
+        impl<'a> $crate::arrow::CatFamily<'a> for $family {
+            type M<A, B>
+                = $circ<'a, A, B>
+            where
+                A: 'a,
+                B: 'a;
+
+            fn id<A>() -> $circ<'a, A, A>
+            where
+                A: 'a,
+            {
+                $circ::from_step(|a| a)
+            }
+
+            // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
+            fn then<A, B, C>(
+                producer: $circ<'a, A, B>,
+                consumer: $circ<'a, B, C>,
+            ) -> $circ<'a, A, C>
+            where
+                A: 'a,
+                B: 'a,
+                C: 'a,
+            {
+                use $crate::impls::stages::{append, Entry, Repr, Slot, MAX_NESTED};
+
+                let depth = producer.depth() + consumer.depth();
+                match (producer.repr, consumer.repr) {
+                    (Repr::Nested { step: mut f, .. }, Repr::Nested { step: mut g, .. })
+                        if depth <= MAX_NESTED =>
+                    {
+                        $circ::nested(move |a| g(f(a)), depth)
+                    }
+                    (producer, consumer) => {
+                        let (input, mut stages, mid) = $circ { repr: producer }.into_flat();
+                        let output = match consumer {
+                            // the last stage reads the producer's output directly
+                            Repr::Nested { step: mut g, .. } => {
+                                let output = Slot::new();
+                                let out = output.clone();
+                                let stage: Step<'a, bool, ()> =
+                                    Box::new(move |_: bool| out.set(mid.take().map(&mut g)));
+                                stages.push_back(Entry::Stage(stage));
+                                output
+                            }
+                            consumer => {
+                                let (consumer_input, consumer_stages, output) =
+                                    $circ { repr: consumer }.into_flat();
+                                let connect: Step<'a, bool, ()> =
+                                    Box::new(move |_: bool| consumer_input.set(mid.take()));
+                                stages = append(stages, Entry::Stage(connect), consumer_stages);
+                                output
+                            }
+                        };
+                        $circ {
+                            repr: Repr::Flat {
+                                input,
+                                stages,
+                                output,
+                            },
+                        }
+                    }
+                }
+            }
+        }
+
+        impl<'a, E $(: $bound)?> $crate::arrow::Holds<'a, E> for $family {
+            fn arrow_with<A, B, F>(env: E, f: F) -> $circ<'a, A, B>
+            where
+                F: Fn(&E, A) -> B + Copy + Send + Sync + 'a,
+                E: 'a,
+                A: 'a,
+                B: 'a,
+            {
+                $circ::from_step(move |a| f(&env, a))
+            }
+
+            fn register(init: E) -> $circ<'a, Option<E>, Option<E>>
+            where
+                E: 'a,
+            {
+                let mut held = Some(init);
+                $circ::from_step(move |e| core::mem::replace(&mut held, e))
+            }
+        }
+
+        impl<'a> $crate::arrow::ArrowFamily<'a> for $family {
+            // first :: m a b -> m (a, c) (b, c)
+            fn fst<A, B, C>(circ: $circ<'a, A, B>) -> $circ<'a, (A, C), (B, C)>
+            where
+                A: 'a,
+                B: 'a,
+                C: 'a,
+            {
+                circ.wrap($crate::impls::stages::Fst)
+            }
+        }
+
+        impl<'a> $crate::arrow::ChoiceFamily<'a> for $family {
+            // left :: a b c -> a (Either b d) (Either c d)
+            fn left<A, B, D>(
+                circ: $circ<'a, A, B>,
+            ) -> $circ<'a, $crate::impls::either::Either<A, D>, $crate::impls::either::Either<B, D>>
+            where
+                A: 'a,
+                B: 'a,
+                D: 'a,
+            {
+                circ.wrap($crate::impls::stages::Left)
+            }
+        }
+
+        impl<'a> $crate::arrow::LoopFamily<'a> for $family {
+            fn loop_through<A, B, D>(
+                circ: $circ<'a, (A, D), (B, D)>,
+                mut register: $circ<'a, Option<D>, Option<D>>,
+            ) -> $circ<'a, A, B>
+            where
+                A: 'a,
+                B: 'a,
+                D: 'a,
+            {
+                circ.wrap($crate::impls::stages::LoopD(move |d| register.step(d)))
+            }
+        }
+
+        impl<'a, A, B> $circ<'a, A, B> {
+            /// Feeds one input through the circuit, updating its state in place.
+            pub fn step(&mut self, a: A) -> B {
+                $crate::impls::stages::step(&mut self.repr, a)
+            }
+
+            /// The output for a single input, discarding the rest of the circuit.
+            pub fn output(mut self, a: A) -> B {
+                self.step(a)
+            }
+
+            /// Runs every input and collects the outputs, leaving the circuit in the
+            /// state after the last one.
+            pub fn run_collect<I>(&mut self, xs: I) -> Vec<B>
+            where
+                I: IntoIterator<Item = A>,
+            {
+                xs.into_iter().map(|a| self.step(a)).collect()
+            }
+        }
+
+        impl<'a, A: 'a, B: 'a> $circ<'a, A, B> {
+            fn nested<F>(f: F, depth: usize) -> Self
+            where
+                F: FnMut(A) -> B $(+ $bound)? + 'a,
+            {
+                Self {
+                    repr: $crate::impls::stages::Repr::Nested {
+                        step: Box::new(f),
+                        depth,
+                    },
+                }
+            }
+
+            fn depth(&self) -> usize {
+                $crate::impls::stages::depth(&self.repr)
+            }
+
+            #[allow(clippy::type_complexity)]
+            fn into_flat(
+                self,
+            ) -> (
+                $crate::impls::stages::Slot<A>,
+                alloc::collections::VecDeque<
+                    $crate::impls::stages::Entry<Step<'a, bool, ()>, Link<'a>>,
+                >,
+                $crate::impls::stages::Slot<B>,
+            ) {
+                use $crate::impls::stages::{Entry, Repr, Slot};
+
+                match self.repr {
+                    Repr::Nested { mut step, .. } => {
+                        let (input, output) = (Slot::new(), Slot::new());
+                        let (inp, out) = (input.clone(), output.clone());
+                        let stage: Step<'a, bool, ()> =
+                            Box::new(move |_: bool| out.set(inp.take().map(&mut step)));
+                        (input, [Entry::Stage(stage)].into(), output)
+                    }
+                    Repr::Flat {
+                        input,
+                        stages,
+                        output,
+                    } => (input, stages, output),
+                    Repr::Poisoned => {
+                        Self::from_step(|_| panic!("a step of the circuit panicked")).into_flat()
+                    }
+                }
+            }
+
+            // Runs the circuit inside one of type `A0 -> B0`, as `w` says. Around
+            // a flat circuit, or past `MAX_NESTED` calls deep, `w` becomes a stage
+            // before the circuit's own and the `Exit` after them.
+            fn wrap<A0, B0, W>(mut self, mut w: W) -> $circ<'a, A0, B0>
+            where
+                A0: 'a,
+                B0: 'a,
+                W: $crate::impls::stages::Wrapper<A0, A, B, B0> $(+ $bound)? + 'a,
+                W::Rest: 'a,
+            {
+                use $crate::impls::stages::{Entry, Repr, Slot, MAX_NESTED};
+
+                let depth = self.depth() + 1;
+                if matches!(self.repr, Repr::Nested { .. }) && depth <= MAX_NESTED {
+                    let step = move |a0| {
+                        let (a, rest) = w.enter(a0);
+                        let b = a.map(|a| self.step(a));
+                        w.exit(b, rest)
+                    };
+                    return $circ::nested(step, depth);
+                }
+                let (inner_input, mut stages, inner_output) = self.into_flat();
+                let (input, output, rest) = (Slot::new(), Slot::new(), Slot::new());
+                let (inp, out) = (input.clone(), output.clone());
+                let stage: Step<'a, bool, ()> = Box::new(move |exit: bool| {
+                    if exit {
+                        let b = inner_output.take();
+                        out.set(rest.take().map(|rest| w.exit(b, rest)));
+                    } else if let Some(a0) = inp.take() {
+                        let (a, kept) = w.enter(a0);
+                        inner_input.set(a);
+                        rest.set(Some(kept));
+                    }
+                });
+                stages.push_front(Entry::Stage(stage));
+                stages.push_back(Entry::Exit(stages.len()));
+                $circ {
+                    repr: Repr::Flat {
+                        input,
+                        stages,
+                        output,
+                    },
+                }
+            }
+
+            /// A circuit that runs `f` on every step, which may keep its own state.
+            pub fn from_step<F>(f: F) -> Self
+            where
+                F: FnMut(A) -> B $(+ $bound)? + 'a,
+            {
+                Self::nested(f, 1)
+            }
+
+            /// A circuit that threads an accumulator from each step to the next.
+            pub fn accum<Acc, F>(acc: Acc, mut f: F) -> Self
+            where
+                Acc: $($bound +)? 'a,
+                F: FnMut(A, Acc) -> (B, Acc) $(+ $bound)? + 'a,
+            {
+                // only empty while `f` runs
+                let mut acc = Some(acc);
+                Self::from_step(move |a| {
+                    let taken = acc.take().expect("a step of the circuit panicked");
+                    let (res, acc_new) = f(a, taken);
+                    acc = Some(acc_new);
+                    res
+                })
+            }
+
+            /// Like `accum`, where the output is the new accumulator.
+            pub fn accum_dup<F>(acc: B, mut f: F) -> Self
+            where
+                F: FnMut(A, B) -> B $(+ $bound)? + 'a,
+                B: Clone $(+ $bound)?,
+            {
+                Self::accum(acc, move |a, b| {
+                    let b_new = f(a, b);
+                    (b_new.clone(), b_new)
+                })
+            }
+        }
+    };
+}
+
+pub(crate) use circuit_impls;
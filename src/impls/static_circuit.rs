@@ -0,0 +1,454 @@
+//! Circuits whose structure is part of their type.
+//!
+//! Each combinator wraps its arguments in a generic struct, e.g.
+//! `arr(f).then(g)` is a `Then<Arr<F>, G>`, so a whole pipeline is a single
+//! concrete type that the compiler can inline, with no boxing or dynamic
+//! calls. The types can't share a family, so these are not instances of the
+//! arrow traits; `into_dyn` converts to a `Circuit` when that is needed.
+
+#[cfg(feature = "alloc")]
+use crate::impls::circuit::Circuit;
+use crate::impls::either::Either;
+#[cfg(feature = "alloc")]
+use crate::snapshot::{Persist, Snapshot, SnapshotError, Value};
+
+/// A circuit from `A` that is stepped in place.
+pub trait StaticCircuit<A> {
+    type Output;
+
+    fn step(&mut self, a: A) -> Self::Output;
+
+    // (>>>) :: Category cat => cat a b -> cat b c -> cat a c
+    fn then<G>(self, consumer: G) -> Then<Self, G>
+    where
+        G: StaticCircuit<Self::Output>,
+        Self: Sized,
+    {
+        Then(self, consumer)
+    }
+
+    // (>>^) :: Arrow m => m b c -> (c -> d) -> m b d
+    fn then_pure<F, C>(self, f: F) -> Then<Self, Arr<F>>
+    where
+        F: FnMut(Self::Output) -> C,
+        Self: Sized,
+    {
+        Then(self, Arr(f))
+    }
+
+    // first :: m a b -> m (a, c) (b, c)
+    fn fst(self) -> First<Self>
+    where
+        Self: Sized,
+    {
+        First(self)
+    }
+
+    // (***) :: m b c -> m b' c' -> m (b,b') (c,c')
+    fn both<G>(self, g: G) -> Both<Self, G>
+    where
+        Self: Sized,
+    {
+        Both(self, g)
+    }
+
+    // (&&&) :: m a b -> m a b' -> m a (b,b')
+    fn dup<G>(self, g: G) -> Dup<Self, G>
+    where
+        G: StaticCircuit<A>,
+        A: Clone,
+        Self: Sized,
+    {
+        Dup(self, g)
+    }
+
+    // left :: m b c -> m (Either b d) (Either c d)
+    fn left(self) -> Left<Self>
+    where
+        Self: Sized,
+    {
+        Left(self)
+    }
+
+    // (|||) :: m a b -> m c b -> m (Either a c) b
+    fn owise<G>(self, g: G) -> Owise<Self, G>
+    where
+        Self: Sized,
+    {
+        Owise(self, g)
+    }
+
+    /// Erases the type of the circuit, boxing it once.
+    #[cfg(feature = "alloc")]
+    fn into_dyn<'a>(mut self) -> Circuit<'a, A, Self::Output>
+    where
+        Self: Sized + 'a,
+        A: 'a,
+        Self::Output: 'a,
+    {
+        Circuit::from_step(move |a| self.step(a))
+    }
+}
+
+/// Runs a function in feedback with the value it produced on the previous
+/// step, as in `LoopFamily::loop_d`.
+pub trait StaticLoop<A, D>: StaticCircuit<(A, D)> {
+    // loopD :: d -> m (a, d) (b, d) -> m a b
+    fn loop_d(self, init: D) -> LoopD<Self, D>
+    where
+        Self: Sized,
+    {
+        LoopD(self, Some(init))
+    }
+}
+
+impl<T, A, D> StaticLoop<A, D> for T where T: StaticCircuit<(A, D)> {}
+
+// arr :: (a -> b) -> m a b
+pub fn arr<F>(f: F) -> Arr<F> {
+    Arr(f)
+}
+
+/// A circuit that threads an accumulator from each step to the next.
+pub fn accum<S, F>(acc: S, f: F) -> Accum<S, F> {
+    Accum(Some(acc), f)
+}
+
+pub struct Arr<F>(F);
+
+pub struct Accum<S, F>(Option<S>, F);
+
+pub struct Then<F, G>(F, G);
+
+pub struct First<F>(F);
+
+pub struct Both<F, G>(F, G);
+
+pub struct Dup<F, G>(F, G);
+
+pub struct Left<F>(F);
+
+pub struct Owise<F, G>(F, G);
+
+pub struct LoopD<F, D>(F, Option<D>);
+
+impl<F, A, B> StaticCircuit<A> for Arr<F>
+where
+    F: FnMut(A) -> B,
+{
+    type Output = B;
+
+    fn step(&mut self, a: A) -> B {
+        (self.0)(a)
+    }
+}
+
+impl<S, F, A, B> StaticCircuit<A> for Accum<S, F>
+where
+    F: FnMut(A, S) -> (B, S),
+{
+    type Output = B;
+
+    fn step(&mut self, a: A) -> B {
+        // only empty while the function runs
+        let acc = self.0.take().expect("a step of the circuit panicked");
+        let (b, acc) = (self.1)(a, acc);
+        self.0 = Some(acc);
+        b
+    }
+}
+
+impl<F, G, A> StaticCircuit<A> for Then<F, G>
+where
+    F: StaticCircuit<A>,
+    G: StaticCircuit<F::Output>,
+{
+    type Output = G::Output;
+
+    fn step(&mut self, a: A) -> G::Output {
+        self.1.step(self.0.step(a))
+    }
+}
+
+impl<F, A, C> StaticCircuit<(A, C)> for First<F>
+where
+    F: StaticCircuit<A>,
+{
+    type Output = (F::Output, C);
+
+    fn step(&mut self, (a, c): (A, C)) -> (F::Output, C) {
+        (self.0.step(a), c)
+    }
+}
+
+impl<F, G, A, A1> StaticCircuit<(A, A1)> for Both<F, G>
+where
+    F: StaticCircuit<A>,
+    G: StaticCircuit<A1>,
+{
+    type Output = (F::Output, G::Output);
+
+    fn step(&mut self, (a, a1): (A, A1)) -> Self::Output {
+        (self.0.step(a), self.1.step(a1))
+    }
+}
+
+impl<F, G, A> StaticCircuit<A> for Dup<F, G>
+where
+    F: StaticCircuit<A>,
+    G: StaticCircuit<A>,
+    A: Clone,
+{
+    type Output = (F::Output, G::Output);
+
+    fn step(&mut self, a: A) -> Self::Output {
+        (self.0.step(a.clone()), self.1.step(a))
+    }
+}
+
+impl<F, A, D> StaticCircuit<Either<A, D>> for Left<F>
+where
+    F: StaticCircuit<A>,
+{
+    type Output = Either<F::Output, D>;
+
+    fn step(&mut self, either: Either<A, D>) -> Self::Output {
+        match either {
+            Either::Left(a) => Either::Left(self.0.step(a)),
+            Either::Right(d) => Either::Right(d),
+        }
+    }
+}
+
+impl<F, G, A, C> StaticCircuit<Either<A, C>> for Owise<F, G>
+where
+    F: StaticCircuit<A>,
+    G: StaticCircuit<C, Output = F::Output>,
+{
+    type Output = F::Output;
+
+    fn step(&mut self, either: Either<A, C>) -> F::Output {
+        match either {
+            Either::Left(a) => self.0.step(a),
+            Either::Right(c) => self.1.step(c),
+        }
+    }
+}
+
+impl<F, A, B, D> StaticCircuit<A> for LoopD<F, D>
+where
+    F: StaticCircuit<(A, D), Output = (B, D)>,
+{
+    type Output = B;
+
+    fn step(&mut self, a: A) -> B {
+        let d = self.1.take().expect("a step of the circuit panicked");
+        let (b, d) = self.0.step((a, d));
+        self.1 = Some(d);
+        b
+    }
+}
+
+// The state of a static circuit is in its fields, so a snapshot follows the
+// structure of the type: `()` for `arr`, the accumulator of `accum`, and a
+// pair for the combinators of two circuits.
+#[cfg(feature = "alloc")]
+impl<F> Snapshot for Arr<F> {
+    type State = ();
+
+    fn snapshot(&self) -> Value {
+        Value::Unit
+    }
+
+    fn read_state(snapshot: &Value) -> Result<(), SnapshotError> {
+        snapshot.expect_unit()
+    }
+
+    fn put_state(&mut self, (): ()) {}
+}
+
+#[cfg(feature = "alloc")]
+impl<S: Persist, F> Snapshot for Accum<S, F> {
+    type State = S;
+
+    fn snapshot(&self) -> Value {
+        let acc = self.0.as_ref().expect("a step of the circuit panicked");
+        acc.to_value()
+    }
+
+    fn read_state(snapshot: &Value) -> Result<S, SnapshotError> {
+        S::from_value(snapshot)
+    }
+
+    fn put_state(&mut self, acc: S) {
+        self.0 = Some(acc);
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<F: Snapshot, D: Persist> Snapshot for LoopD<F, D> {
+    type State = (F::State, D);
+
+    fn snapshot(&self) -> Value {
+        let d = self.1.as_ref().expect("a step of the circuit panicked");
+        Value::pair(self.0.snapshot(), d.to_value())
+    }
+
+    fn read_state(snapshot: &Value) -> Result<(F::State, D), SnapshotError> {
+        let (f, d) = snapshot.as_pair()?;
+        Ok((F::read_state(f)?, D::from_value(d)?))
+    }
+
+    fn put_state(&mut self, (f, d): (F::State, D)) {
+        self.0.put_state(f);
+        self.1 = Some(d);
+    }
+}
+
+macro_rules! snapshot_one {
+    ($($t:ident),*) => {$(
+        #[cfg(feature = "alloc")]
+        impl<F: Snapshot> Snapshot for $t<F> {
+            type State = F::State;
+
+            fn snapshot(&self) -> Value {
+                self.0.snapshot()
+            }
+
+            fn read_state(snapshot: &Value) -> Result<F::State, SnapshotError> {
+                F::read_state(snapshot)
+            }
+
+            fn put_state(&mut self, state: F::State) {
+                self.0.put_state(state)
+            }
+        }
+    )*};
+}
+
+macro_rules! snapshot_two {
+    ($($t:ident),*) => {$(
+        #[cfg(feature = "alloc")]
+        impl<F: Snapshot, G: Snapshot> Snapshot for $t<F, G> {
+            type State = (F::State, G::State);
+
+            fn snapshot(&self) -> Value {
+                Value::pair(self.0.snapshot(), self.1.snapshot())
+            }
+
+            fn read_state(snapshot: &Value) -> Result<Self::State, SnapshotError> {
+                let (f, g) = snapshot.as_pair()?;
+                Ok((F::read_state(f)?, G::read_state(g)?))
+            }
+
+            fn put_state(&mut self, (f, g): Self::State) {
+                self.0.put_state(f);
+                self.1.put_state(g);
+            }
+        }
+    )*};
+}
+
+snapshot_one!(First, Left);
+snapshot_two!(Then, Both, Dup, Owise);
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::arrow::*;
+    use crate::impls::circuit::arrow;
+
+    #[test]
+    fn test_static() {
+        let mut collatz = arr(|n: u64| Either::from((n.is_multiple_of(2), n)))
+            .then(arr(|n| 3 * n + 1).owise(arr(|n| n / 2)))
+            .then(arr(|n| n).dup(accum(0, |_, steps: usize| (steps + 1, steps + 1))));
+        let mut n = 6;
+        let mut steps = 0;
+        while n != 1 {
+            (n, steps) = collatz.step(n);
+        }
+        assert_eq!(steps, 8);
+
+        let mut pairs = arr(|n: i32| n + 1).both(arr(|s: &str| s.len())).fst();
+        assert_eq!(pairs.step(((1, "ab"), 'c')), ((2, 2), 'c'));
+
+        let mut tagged = arr(|n: i32| n * 2).left();
+        assert_eq!(tagged.step(Either::<_, &str>::Left(4)), Either::Left(8));
+        assert_eq!(tagged.step(Either::Right("skip")), Either::Right("skip"));
+    }
+
+    #[test]
+    fn test_into_dyn() {
+        let sum = arr(|(n, total): (i32, i32)| (n + total, n + total)).loop_d(0);
+        let res: Vec<_> = sum.into_dyn().run([1, 2, 3]).collect();
+        assert_eq!(res, vec![1, 3, 6]);
+
+        // the erased circuit composes with the dynamic combinators
+        let shown = arr(|n: i32| n * 10)
+            .into_dyn()
+            .then(arrow(|n: i32| n.to_string()));
+        assert_eq!(shown.output(4), "40");
+    }
+
+    // the running mean of the even inputs or the count of odd ones, and the
+    // running total of all of them
+    fn stats() -> impl StaticCircuit<i64, Output = (i64, Either<f64, u32>)> + Snapshot {
+        let mean = accum((0.0, 0), |x: i64, (sum, n): (f64, u32)| {
+            let (sum, n) = (sum + x as f64, n + 1);
+            (sum / n as f64, (sum, n))
+        });
+        let odd = accum(0, |_: i64, n: u32| (n + 1, n + 1));
+        let total = arr(|(x, sum): (i64, i64)| (x + sum, x + sum)).loop_d(0);
+        let classify = mean
+            .left()
+            .then(arr(Either::flip))
+            .then(odd.left())
+            .then(arr(Either::flip));
+        arr(|x: i64| (Either::from((x % 2 != 0, x)), x))
+            .then(classify.fst())
+            .then(arr(|(e, x)| (x, e)))
+            .then(total.fst())
+    }
+
+    #[test]
+    fn test_snapshot() {
+        let inputs = [4, 7, 10, 3, 3, 8, 1, 6];
+        let mut expected = stats();
+        let expected: Vec<_> = inputs.iter().map(|&x| expected.step(x)).collect();
+
+        let mut circuit = stats();
+        for &x in &inputs[..5] {
+            circuit.step(x);
+        }
+        let path = std::env::temp_dir().join(format!("arrows-snapshot-{}", std::process::id()));
+        std::fs::write(&path, circuit.snapshot().to_string()).unwrap();
+        drop(circuit);
+
+        // a new process would build the same circuit and resume from the file
+        let mut resumed = stats();
+        let saved: Value = std::fs::read_to_string(&path).unwrap().parse().unwrap();
+        std::fs::remove_file(&path).unwrap();
+        resumed.restore(&saved).unwrap();
+        assert_eq!(resumed.snapshot(), saved);
+        let rest: Vec<_> = inputs[5..].iter().map(|&x| resumed.step(x)).collect();
+        assert_eq!(rest, expected[5..]);
+
+        let mut other = accum(0, |x: i32, n: i32| (x + n, n + 1)).then(arr(|x| x * 2));
+        assert_eq!(
+            other.restore(&saved),
+            Err(SnapshotError::Mismatch("an integer"))
+        );
+        other
+            .restore(&Value::pair(Value::Int(5), Value::Unit))
+            .unwrap();
+        assert_eq!(other.step(1), 12);
+
+        // a snapshot that only fits the first half changes nothing
+        assert_eq!(
+            other.restore(&Value::pair(Value::Int(0), Value::Int(0))),
+            Err(SnapshotError::Mismatch("()"))
+        );
+        assert_eq!(other.step(1), 14);
+    }
+}
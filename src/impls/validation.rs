@@ -0,0 +1,326 @@
+use crate::{impls::either::Either, monad::*};
+#[cfg(feature = "alloc")]
+use alloc::{vec, vec::Vec};
+
+/// Like `Either`, but `lift_a2` keeps going after a failure and combines the
+/// errors of both sides, so every failed check is reported at once.
+///
+/// There is deliberately no Monad instance: `bind` can only run the next
+/// computation after a success, which would make errors short-circuit and
+/// disagree with `apply`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Validation<E, A> {
+    Failure(E),
+    Success(A),
+}
+
+pub struct ValidationFamily<E>(core::marker::PhantomData<E>);
+
+impl<E> Kind for ValidationFamily<E> {
+    type M<T> = Validation<E, T>;
+}
+
+impl<E, A> Hkt<A> for Validation<E, A> {
+    type Kind = ValidationFamily<E>;
+}
+
+impl<'a, E> FunctorFamily<'a> for ValidationFamily<E> {
+    fn fmap<A, B, F>(validation: Validation<E, A>, f: F) -> Validation<E, B>
+    where
+        F: Fn(A) -> B,
+    {
+        match validation {
+            Validation::Success(a) => Validation::Success(f(a)),
+            Validation::Failure(e) => Validation::Failure(e),
+        }
+    }
+}
+
+impl<'a, E> MapMutFamily<'a> for ValidationFamily<E> {
+    fn fmap_mut<A, B, F>(validation: Validation<E, A>, f: F) -> Validation<E, B>
+    where
+        F: FnMut(A) -> B,
+    {
+        Self::fmap_once(validation, f)
+    }
+}
+
+impl<'a, E> MapOnceFamily<'a> for ValidationFamily<E> {
+    fn fmap_once<A, B, F>(validation: Validation<E, A>, f: F) -> Validation<E, B>
+    where
+        F: FnOnce(A) -> B,
+    {
+        match validation {
+            Validation::Success(a) => Validation::Success(f(a)),
+            Validation::Failure(e) => Validation::Failure(e),
+        }
+    }
+}
+
+impl<'a, E: Clone> FunctorRefFamily<'a> for ValidationFamily<E> {
+    fn fmap_ref<'r, A, B, F>(validation: &'r Validation<E, A>, mut f: F) -> Validation<E, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        match validation {
+            Validation::Success(a) => Validation::Success(f(a)),
+            Validation::Failure(e) => Validation::Failure(e.clone()),
+        }
+    }
+}
+
+impl<'a, E> FunctorMutFamily<'a> for ValidationFamily<E> {
+    fn fmap_in_place<A, F>(validation: &mut Validation<E, A>, mut f: F)
+    where
+        F: FnMut(&mut A),
+    {
+        if let Validation::Success(a) = validation {
+            f(a)
+        }
+    }
+}
+
+// Only a semigroup of errors can be accumulated, so unlike the other
+// families this one is a functor for every `E` but only sometimes
+// applicative.
+impl<'a, E: Semigroup> ApplicativeFamily<'a> for ValidationFamily<E> {
+    fn pure<A>(value: A) -> Validation<E, A> {
+        Validation::Success(value)
+    }
+
+    fn lift_a2<A, B, C, F>(va: Validation<E, A>, vb: Validation<E, B>, f: F) -> Validation<E, C>
+    where
+        F: Fn(A, B) -> C,
+    {
+        match (va, vb) {
+            (Validation::Success(a), Validation::Success(b)) => Validation::Success(f(a, b)),
+            (Validation::Failure(e1), Validation::Failure(e2)) => {
+                Validation::Failure(e1.combine(e2))
+            }
+            (Validation::Failure(e), _) | (_, Validation::Failure(e)) => Validation::Failure(e),
+        }
+    }
+}
+
+impl<'a, E: Semigroup> SelectiveFamily<'a> for ValidationFamily<E> {
+    // select :: f (Either a b) -> f (a -> b) -> f b
+    fn select<A, B, F>(x: Validation<E, Either<A, B>>, f: Validation<E, F>) -> Validation<E, B>
+    where
+        F: Fn(A) -> B,
+    {
+        match x {
+            Validation::Success(Either::Left(a)) => f.and_then(|f| Validation::Success(f(a))),
+            Validation::Success(Either::Right(b)) => Validation::Success(b),
+            Validation::Failure(e) => Validation::Failure(e),
+        }
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl<E, A> Validation<Vec<E>, A> {
+    /// A failure holding a single error.
+    pub fn fail(error: E) -> Self {
+        Self::Failure(vec![error])
+    }
+}
+
+impl<E, A> Validation<E, A> {
+    pub fn is_success(&self) -> bool {
+        matches!(self, Self::Success(_))
+    }
+
+    pub fn ok(self) -> Option<A> {
+        match self {
+            Self::Success(a) => Some(a),
+            Self::Failure(_) => None,
+        }
+    }
+
+    pub fn map_failure<F, E1>(self, f: F) -> Validation<E1, A>
+    where
+        F: FnOnce(E) -> E1,
+    {
+        match self {
+            Self::Success(a) => Validation::Success(a),
+            Self::Failure(e) => Validation::Failure(f(e)),
+        }
+    }
+
+    /// Runs a computation that depends on a successful value. Unlike
+    /// `lift_a2`, this short-circuits on the first failure.
+    pub fn and_then<F, B>(self, f: F) -> Validation<E, B>
+    where
+        F: FnOnce(A) -> Validation<E, B>,
+    {
+        match self {
+            Self::Success(a) => f(a),
+            Self::Failure(e) => Validation::Failure(e),
+        }
+    }
+}
+
+impl<E, A> From<Either<E, A>> for Validation<E, A> {
+    fn from(either: Either<E, A>) -> Self {
+        match either {
+            Either::Left(e) => Self::Failure(e),
+            Either::Right(a) => Self::Success(a),
+        }
+    }
+}
+
+impl<E, A> From<Validation<E, A>> for Either<E, A> {
+    fn from(validation: Validation<E, A>) -> Self {
+        match validation {
+            Validation::Failure(e) => Self::Left(e),
+            Validation::Success(a) => Self::Right(a),
+        }
+    }
+}
+
+impl<E, A> From<Result<A, E>> for Validation<E, A> {
+    fn from(result: Result<A, E>) -> Self {
+        match result {
+            Ok(a) => Self::Success(a),
+            Err(e) => Self::Failure(e),
+        }
+    }
+}
+
+impl<E, A> From<Validation<E, A>> for Result<A, E> {
+    fn from(validation: Validation<E, A>) -> Self {
+        match validation {
+            Validation::Success(a) => Ok(a),
+            Validation::Failure(e) => Err(e),
+        }
+    }
+}
+
+// traverse :: (a -> f b) -> [a] -> f [b]
+#[cfg(feature = "alloc")]
+pub fn traverse<T, F, E, A>(xs: impl IntoIterator<Item = T>, f: F) -> Validation<E, Vec<A>>
+where
+    F: Fn(T) -> Validation<E, A>,
+    E: Semigroup,
+{
+    xs.into_iter()
+        .fold(Validation::Success(Vec::new()), |acc, x| {
+            acc.lift_a2(f(x), |mut xs, a| {
+                xs.push(a);
+                xs
+            })
+        })
+}
+
+// sequence :: [f a] -> f [a]
+#[cfg(feature = "alloc")]
+pub fn sequence<E, A>(xs: impl IntoIterator<Item = Validation<E, A>>) -> Validation<E, Vec<A>>
+where
+    E: Semigroup,
+{
+    traverse(xs, core::convert::identity)
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::combinators::traverse_ref;
+
+    #[derive(Debug, PartialEq)]
+    struct User {
+        name: String,
+        age: u8,
+    }
+
+    fn check_name(name: &str) -> Validation<Vec<String>, String> {
+        if name.is_empty() {
+            Validation::fail("name is empty".to_string())
+        } else {
+            Validation::Success(name.to_string())
+        }
+    }
+
+    fn check_age(age: i32) -> Validation<Vec<String>, u8> {
+        u8::try_from(age)
+            .map_err(|_| vec![format!("{} is not a valid age", age)])
+            .into()
+    }
+
+    #[test]
+    fn test_validation() {
+        let user =
+            |name, age| check_name(name).lift_a2(check_age(age), |name, age| User { name, age });
+
+        let alice = User {
+            name: "alice".to_string(),
+            age: 30,
+        };
+        assert_eq!(user("alice", 30), Validation::Success(alice));
+
+        // both errors are reported
+        assert_eq!(
+            user("", -1),
+            Validation::Failure(vec![
+                "name is empty".to_string(),
+                "-1 is not a valid age".to_string()
+            ])
+        );
+
+        // functor
+        assert_eq!(check_age(5).fmap(|n| n * 2), Validation::Success(10));
+
+        // conversions
+        let either: Either<Vec<String>, u8> = check_age(300).into();
+        assert!(matches!(either, Either::Left(_)));
+        let back: Validation<Vec<String>, u8> = Either::Right(3).into();
+        assert_eq!(back, Validation::Success(3));
+
+        // any semigroup collects errors
+        let a: Validation<String, ()> = Validation::Failure("a".to_string());
+        let b = Validation::Failure("b".to_string());
+        assert_eq!(
+            a.lift_a2(b, |(), ()| ()),
+            Validation::Failure("ab".to_string())
+        );
+    }
+
+    #[test]
+    fn test_traverse() {
+        let ages = vec![1, -2, 3, 1000];
+        let res = traverse(ages, check_age);
+        assert_eq!(
+            res,
+            Validation::Failure(vec![
+                "-2 is not a valid age".to_string(),
+                "1000 is not a valid age".to_string()
+            ])
+        );
+
+        let res = sequence(vec![check_age(1), check_age(2)]);
+        assert_eq!(res, Validation::Success(vec![1, 2]));
+
+        // the names are borrowed, and every failure is still collected
+        let names = ["alice", "", "bob", ""];
+        let res = traverse_ref(&names, |name| check_name(name));
+        assert_eq!(
+            res.fmap_ref(Vec::len),
+            Validation::Failure(vec![
+                "name is empty".to_string(),
+                "name is empty".to_string()
+            ])
+        );
+        assert_eq!(names.len(), 4);
+    }
+
+    #[test]
+    fn test_selective() {
+        type V<T> = Validation<Vec<String>, T>;
+        let needs_age: V<bool> = Validation::Success(true);
+        let age = check_age(-5).fmap(|_| ());
+        assert!(!ValidationFamily::when_s(needs_age, age.clone()).is_success());
+
+        // the age check is skipped when it isn't needed
+        let needs_age: V<bool> = Validation::Success(false);
+        assert!(ValidationFamily::when_s(needs_age, age).is_success());
+    }
+}
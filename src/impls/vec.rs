@@ -1,71 +1,111 @@
-use crate::monad::*;
+use crate::{impls::either::Either, monad::*};
+use alloc::{vec, vec::Vec};
 
 pub struct VecFamily;
 
-impl FunctorFamily for VecFamily {
+impl Kind for VecFamily {
     type M<T> = Vec<T>;
 }
-impl<A> Functor<A> for Vec<A> {
-    type FFamily = VecFamily;
+impl<A> Hkt<A> for Vec<A> {
+    type Kind = VecFamily;
+}
 
+impl<'a> FunctorFamily<'a> for VecFamily {
     // fmap (<$>) :: (a -> b) -> f a -> f b
-    fn fmap<F, B>(self, f: F) -> Vec<B>
+    fn fmap<A, B, F>(vec: Vec<A>, f: F) -> Vec<B>
     where
         F: Fn(A) -> B,
     {
-        self.into_iter().map(f).collect()
+        vec.into_iter().map(f).collect()
     }
 }
-impl ApplicativeFamily for VecFamily {
-    type M<T> = Vec<T>;
+impl<'a> MapMutFamily<'a> for VecFamily {
+    // elements are visited front to back
+    fn fmap_mut<A, B, F>(vec: Vec<A>, f: F) -> Vec<B>
+    where
+        F: FnMut(A) -> B,
+    {
+        vec.into_iter().map(f).collect()
+    }
 }
-impl<A> Applicative<A> for Vec<A> {
-    type AFamily = VecFamily;
-
+impl<'a> FunctorRefFamily<'a> for VecFamily {
+    fn fmap_ref<'r, A, B, F>(vec: &'r Vec<A>, f: F) -> Vec<B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        vec.iter().map(f).collect()
+    }
+}
+impl<'a> FunctorMutFamily<'a> for VecFamily {
+    fn fmap_in_place<A, F>(vec: &mut Vec<A>, f: F)
+    where
+        F: FnMut(&mut A),
+    {
+        vec.iter_mut().for_each(f)
+    }
+}
+impl<A> Foldable<A> for Vec<A> {
+    // foldl :: (b -> a -> b) -> b -> t a -> b
+    fn fold_left<'a, B, F>(&'a self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &'a A) -> B,
+        A: 'a,
+    {
+        self.iter().fold(init, f)
+    }
+}
+impl<'a> ApplicativeFamily<'a> for VecFamily {
     // a -> f a
-    fn pure(a: A) -> Self {
+    fn pure<A>(a: A) -> Vec<A> {
         vec![a]
     }
 
     // (a -> b -> c) -> f a -> f b -> f c
-    fn lift_a2<F, B, C>(self, vec_b: Vec<B>, f: F) -> Vec<C>
+    fn lift_a2<A, B, C, F>(vec_a: Vec<A>, vec_b: Vec<B>, f: F) -> Vec<C>
     where
         F: Fn(A, B) -> C,
     {
-        self.into_iter()
-            .zip(vec_b.into_iter())
-            .map(|(a, b)| f(a, b))
-            .collect()
+        vec_a.into_iter().zip(vec_b).map(|(a, b)| f(a, b)).collect()
     }
 
     // (<*>) :: f (a -> b) -> f a -> f b
-    fn apply<F, B>(self, fns: Vec<F>) -> Vec<B>
+    fn apply<A, B, F>(vec: Vec<A>, fns: Vec<F>) -> Vec<B>
     where
         F: Fn(A) -> B,
     {
-        fns.into_iter()
-            .zip(self.into_iter())
-            .map(|(f, a)| f(a))
-            .collect()
+        fns.into_iter().zip(vec).map(|(f, a)| f(a)).collect()
     }
 }
 
-impl MonadFamily for VecFamily {
-    type M<T> = Vec<T>;
+// Selects element-wise to agree with the zipping Applicative instance.
+impl<'a> SelectiveFamily<'a> for VecFamily {
+    // select :: f (Either a b) -> f (a -> b) -> f b
+    fn select<A, B, F>(x: Vec<Either<A, B>>, fns: Vec<F>) -> Vec<B>
+    where
+        F: Fn(A) -> B,
+    {
+        x.into_iter()
+            .zip(fns)
+            .map(|(either, f)| match either {
+                Either::Left(a) => f(a),
+                Either::Right(b) => b,
+            })
+            .collect()
+    }
 }
-impl<A> Monad<A> for Vec<A> {
-    type MFamily = VecFamily;
 
+impl<'a> MonadFamily<'a> for VecFamily {
     // bind (>>=) :: m a -> (a -> m b) -> m b
-    fn bind<F, B>(self, f: F) -> Vec<B>
+    fn bind<A, B, F>(vec: Vec<A>, f: F) -> Vec<B>
     where
         F: Fn(A) -> Vec<B>,
     {
-        self.into_iter().flat_map(f).collect()
+        vec.into_iter().flat_map(f).collect()
     }
 
     // compose (>=>) :: (a -> m b) -> (b -> m c) -> a -> m c
-    fn compose<F, G, B, C>(f: F, g: G, a: A) -> Vec<C>
+    fn compose<A, B, C, F, G>(f: F, g: G, a: A) -> Vec<C>
     where
         F: FnOnce(A) -> Vec<B>,
         G: Fn(B) -> Vec<C>,
@@ -74,11 +114,33 @@ impl<A> Monad<A> for Vec<A> {
     }
 
     // m (m a) -> m a
-    fn join(vec_vec: Vec<Vec<A>>) -> Self {
+    fn join<A>(vec_vec: Vec<Vec<A>>) -> Vec<A> {
         vec_vec.into_iter().flatten().collect()
     }
 }
 
+impl<'a> BindMutFamily<'a> for VecFamily {
+    fn bind_mut<A, B, F>(vec: Vec<A>, f: F) -> Vec<B>
+    where
+        F: FnMut(A) -> Vec<B>,
+    {
+        vec.into_iter().flat_map(f).collect()
+    }
+}
+
+impl<'a> MonadPlusFamily<'a> for VecFamily {
+    // mzero :: m a
+    fn mzero<A>() -> Vec<A> {
+        Vec::new()
+    }
+
+    // mplus :: m a -> m a -> m a
+    fn mplus<A>(mut a: Vec<A>, b: Vec<A>) -> Vec<A> {
+        a.extend(b);
+        a
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -110,4 +172,52 @@ mod tests {
         let double_vec = vec![vec.clone()];
         assert_eq!(Vec::join(double_vec), vec);
     }
+
+    #[test]
+    fn test_selective() {
+        let xs = vec![Either::Left(1), Either::Right(20), Either::Left(3)];
+        let f = |x| x * 100;
+        let res = VecFamily::select(xs, vec![f, f, f]);
+        assert_eq!(res, vec![100, 20, 300]);
+
+        let res = VecFamily::if_s(vec![true, false, true], vec![1, 2, 3], vec![4, 5, 6]);
+        assert_eq!(res, vec![1, 5, 3]);
+
+        assert_eq!(
+            VecFamily::when_s(vec![true, false], vec![(), ()]),
+            vec![(), ()]
+        );
+    }
+
+    #[test]
+    fn test_closure_kinds() {
+        // number each element as it is visited
+        let mut next = 0;
+        let res = vec!['a', 'b', 'c'].fmap_mut(|c| {
+            next += 1;
+            (next, c)
+        });
+        assert_eq!(res, vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+
+        let mut seen = Vec::new();
+        let res = vec![1, 2].bind_mut(|n| {
+            seen.push(n);
+            vec![n; n]
+        });
+        assert_eq!(res, vec![1, 2, 2]);
+        assert_eq!(seen, vec![1, 2]);
+    }
+
+    #[test]
+    fn test_by_ref() {
+        let words = vec!["a".to_string(), "bcd".to_string()];
+        // the results may borrow from the original
+        let slices = words.fmap_ref(|w| &w[..1]);
+        assert_eq!(slices, vec!["a", "b"]);
+        assert_eq!(words.fmap_ref(String::len), vec![1, 3]);
+
+        let mut words = words;
+        words.fmap_in_place(|w| w.push('!'));
+        assert_eq!(words, vec!["a!", "bcd!"]);
+    }
 }
@@ -1,6 +1,16 @@
-#![feature(generic_associated_types)]
 // #![feature(unboxed_closures, fn_traits, type_alias_impl_trait)]
+#![cfg_attr(not(any(feature = "std", test)), no_std)]
+
+// `alloc` enables the instances for `Vec`, `String` and the boxed types like
+// `Circuit` and `Lazy`; `std` enables everything that needs an OS.
+#[cfg(feature = "alloc")]
+extern crate alloc;
 
 pub mod arrow;
+pub mod combinators;
 pub mod impls;
+pub mod lift;
 pub mod monad;
+pub mod notation;
+#[cfg(feature = "alloc")]
+pub mod snapshot;
@@ -0,0 +1,201 @@
+//! Lifting functions of more than two arguments into any Applicative.
+//!
+//! `lift_a3` through `lift_a8` are built from repeated `lift_a2`, collecting
+//! the values in a tuple that grows by one element per step. The `lift_a!`
+//! and `zip_a!` macros pick the right function from the number of arguments.
+
+use crate::monad::*;
+
+pub type AOf<FA, A, B> = Of<<FA as Hkt<A>>::Kind, B>;
+
+// Folds `lift_a2` over the remaining arguments. The accumulated tuple is
+// destructured with the argument names themselves, which shadow the
+// applicative values inside each closure.
+macro_rules! lift_chain {
+    ($acc:expr; [$($done:ident),+]; $next:ident; $func:ident) => {
+        $acc.lift_a2($next, move |($($done),+), $next| $func($($done,)+ $next))
+    };
+    ($acc:expr; [$($done:ident),+]; $next:ident, $($rest:ident),+; $func:ident) => {
+        lift_chain!(
+            $acc.lift_a2($next, |($($done),+), $next| ($($done,)+ $next));
+            [$($done,)+ $next];
+            $($rest),+;
+            $func
+        )
+    };
+}
+
+macro_rules! lift_fns {
+    ($lift:ident, $zip:ident; $a:ident: $A:ident, $b:ident: $B:ident, $($x:ident: $X:ident),+) => {
+        // liftA :: (a -> b -> .. -> r) -> f a -> f b -> .. -> f r
+        #[allow(clippy::too_many_arguments)]
+        pub fn $lift<'a, FA, $A, $B, $($X,)+ Out, Func>(
+            $a: FA,
+            $b: AOf<FA, $A, $B>,
+            $($x: AOf<FA, $A, $X>,)+
+            func: Func,
+        ) -> AOf<FA, $A, Out>
+        where
+            FA: Applicative<'a, $A>,
+            Func: Fn($A, $B, $($X),+) -> Out + 'a,
+            $A: 'a,
+            $B: 'a,
+            $($X: 'a,)+
+            Out: 'a,
+        {
+            lift_chain!(zip_a2($a, $b); [$a, $b]; $($x),+; func)
+        }
+
+        // f a -> f b -> .. -> f (a, b, ..)
+        #[allow(clippy::too_many_arguments)]
+        pub fn $zip<'a, FA, $A, $B, $($X),+>(
+            $a: FA,
+            $b: AOf<FA, $A, $B>,
+            $($x: AOf<FA, $A, $X>),+
+        ) -> AOf<FA, $A, ($A, $B, $($X),+)>
+        where
+            FA: Applicative<'a, $A>,
+            $A: 'a,
+            $B: 'a,
+            $($X: 'a,)+
+        {
+            $lift($a, $b, $($x,)+ |$a, $b, $($x),+| ($a, $b, $($x),+))
+        }
+    };
+}
+
+// f a -> f b -> f (a, b)
+pub fn zip_a2<'a, FA, A, B>(a: FA, b: AOf<FA, A, B>) -> AOf<FA, A, (A, B)>
+where
+    FA: Applicative<'a, A>,
+    A: 'a,
+    B: 'a,
+{
+    a.lift_a2(b, |a, b| (a, b))
+}
+
+lift_fns!(lift_a3, zip_a3; a: A, b: B, c: C);
+lift_fns!(lift_a4, zip_a4; a: A, b: B, c: C, d: D);
+lift_fns!(lift_a5, zip_a5; a: A, b: B, c: C, d: D, e: E);
+lift_fns!(lift_a6, zip_a6; a: A, b: B, c: C, d: D, e: E, f: F);
+lift_fns!(lift_a7, zip_a7; a: A, b: B, c: C, d: D, e: E, f: F, g: G);
+lift_fns!(lift_a8, zip_a8; a: A, b: B, c: C, d: D, e: E, f: F, g: G, h: H);
+
+/// Lifts a function of 2 to 8 arguments into an Applicative.
+///
+/// `lift_a!(f; a, b, c)` is `lift_a3(a, b, c, f)`.
+#[macro_export]
+macro_rules! lift_a {
+    ($f:expr; $a:expr, $b:expr $(,)?) => {
+        $crate::monad::Applicative::lift_a2($a, $b, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr $(,)?) => {
+        $crate::lift::lift_a3($a, $b, $c, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr, $d:expr $(,)?) => {
+        $crate::lift::lift_a4($a, $b, $c, $d, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr, $d:expr, $e:expr $(,)?) => {
+        $crate::lift::lift_a5($a, $b, $c, $d, $e, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $g:expr $(,)?) => {
+        $crate::lift::lift_a6($a, $b, $c, $d, $e, $g, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $g:expr, $h:expr $(,)?) => {
+        $crate::lift::lift_a7($a, $b, $c, $d, $e, $g, $h, $f)
+    };
+    ($f:expr; $a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $g:expr, $h:expr, $i:expr $(,)?) => {
+        $crate::lift::lift_a8($a, $b, $c, $d, $e, $g, $h, $i, $f)
+    };
+}
+
+/// Combines 2 to 8 applicative values into an applicative tuple.
+#[macro_export]
+macro_rules! zip_a {
+    ($a:expr, $b:expr $(,)?) => {
+        $crate::lift::zip_a2($a, $b)
+    };
+    ($a:expr, $b:expr, $c:expr $(,)?) => {
+        $crate::lift::zip_a3($a, $b, $c)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr $(,)?) => {
+        $crate::lift::zip_a4($a, $b, $c, $d)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr $(,)?) => {
+        $crate::lift::zip_a5($a, $b, $c, $d, $e)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr $(,)?) => {
+        $crate::lift::zip_a6($a, $b, $c, $d, $e, $f)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr $(,)?) => {
+        $crate::lift::zip_a7($a, $b, $c, $d, $e, $f, $g)
+    };
+    ($a:expr, $b:expr, $c:expr, $d:expr, $e:expr, $f:expr, $g:expr, $h:expr $(,)?) => {
+        $crate::lift::zip_a8($a, $b, $c, $d, $e, $f, $g, $h)
+    };
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::impls::{either::Either, validation::Validation};
+
+    #[test]
+    fn test_option() {
+        let sum4 = |a, b, c, d| a + b + c + d;
+        assert_eq!(lift_a4(Some(1), Some(2), Some(3), Some(4), sum4), Some(10));
+        assert_eq!(lift_a!(sum4; Some(1), None, Some(3), Some(4)), None);
+
+        assert_eq!(zip_a!(Some(1), Some('b'), Some("c")), Some((1, 'b', "c")));
+        assert_eq!(
+            zip_a!(
+                Some(1),
+                Some(2),
+                Some(3),
+                Some(4),
+                Some(5),
+                Some(6),
+                Some(7),
+                Some(8)
+            ),
+            Some((1, 2, 3, 4, 5, 6, 7, 8))
+        );
+        assert_eq!(lift_a!(|a: u8, b: u8| a * b; Some(3), Some(4)), Some(12));
+    }
+
+    #[test]
+    fn test_vec() {
+        // the Vec applicative zips, truncating to the shortest input
+        let res = lift_a!(|a, b, c| a * b * c; vec![1, 2, 3], vec![4, 5, 6], vec![7, 8]);
+        assert_eq!(res, vec![28, 80]);
+
+        let res = zip_a!(
+            vec![1, 2],
+            vec!['a', 'b'],
+            vec![true, false],
+            vec!["x", "y"]
+        );
+        assert_eq!(res, vec![(1, 'a', true, "x"), (2, 'b', false, "y")]);
+    }
+
+    #[test]
+    fn test_either() {
+        type E<T> = Either<&'static str, T>;
+        let a: E<usize> = Either::Right(1);
+        let b: E<usize> = Either::Right(2);
+        let bad: E<usize> = Either::Left("bad");
+        let sum5 = |a, b, c, d, e| a + b + c + d + e;
+
+        assert_eq!(lift_a5(a, b, a, b, a, sum5), Either::Right(7));
+        assert_eq!(lift_a!(sum5; a, b, bad, b, a), Either::Left("bad"));
+        assert_eq!(zip_a!(a, b, a), Either::Right((1, 2, 1)));
+
+        // error accumulation carries over to every arity
+        let fail = |e: &str| Validation::<Vec<String>, u8>::fail(e.to_string());
+        let res = zip_a!(fail("a"), Validation::Success(1), fail("c"));
+        assert_eq!(
+            res,
+            Validation::Failure(vec!["a".to_string(), "c".to_string()])
+        );
+    }
+}
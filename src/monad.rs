@@ -1,93 +1,693 @@
-pub trait FunctorFamily {
-    type M<T>: Functor<T, FFamily = Self>;
+use crate::impls::either::Either;
+#[cfg(feature = "alloc")]
+use alloc::{string::String, vec::Vec};
+pub use arrows_derive::{Foldable, Functor};
+
+/// A type constructor like `Option` or `Either<L, _>`, named by a family
+/// type that is declared once per constructor. `FunctorFamily`,
+/// `ApplicativeFamily` and `MonadFamily` are supertraits over it, so `fmap`,
+/// `lift_a2` and `bind` all produce the same `M<T>`.
+pub trait Kind {
+    type M<T>: Hkt<T, Kind = Self>;
+}
+
+/// A type constructor applied to `A`, e.g. `Option<A>` is `Hkt<A>` with
+/// `Kind = OptionFamily`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a type constructor applied to `{A}`",
+    note = "only values like `Option<A>` or `Vec<A>` can be mapped over, or bound with `<-` in `mdo!`"
+)]
+pub trait Hkt<A> {
+    type Kind: Kind<M<A> = Self>;
 }
 
-pub trait Functor<A> {
-    type FFamily: FunctorFamily<M<A> = Self>;
+/// The type constructor of the family `K` applied to `T`.
+pub type Of<K, T> = <K as Kind>::M<T>;
+
+/// Functors whose mapped functions may be kept until `'a`, as a lazy
+/// functor does; every other family is a functor for any `'a`.
+#[diagnostic::on_unimplemented(message = "`{Self}` is not a functor")]
+pub trait FunctorFamily<'a>: Kind {
+    // fmap (<$>) :: (a -> b) -> f a -> f b
+    fn fmap<A, B, F>(fa: Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+}
 
+/// Method syntax for `FunctorFamily`, implemented for every `Hkt` whose
+/// family is a functor.
+#[diagnostic::on_unimplemented(message = "`{Self}` is not a functor over `{A}`")]
+pub trait Functor<'a, A>: Hkt<A, Kind: FunctorFamily<'a>> {
     // fmap (<$>) :: (a -> b) -> f a -> f b
-    fn fmap<F, B>(self, f: F) -> <Self::FFamily as FunctorFamily>::M<B>
+    fn fmap<F, B>(self, f: F) -> Of<Self::Kind, B>
     where
-        F: Fn(A) -> B;
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
 
     // (<$) :: a -> f b -> f a
-    fn map_replace<B>(self, b: B) -> <Self::FFamily as FunctorFamily>::M<B>
+    fn map_replace<B>(self, b: B) -> Of<Self::Kind, B>
     where
-        B: Clone,
+        A: 'a,
+        B: Clone + 'a,
         Self: Sized,
     {
-        self.fmap(|_| b.clone())
+        self.fmap(move |_| b.clone())
+    }
+}
+
+impl<'a, T, A> Functor<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: FunctorFamily<'a>,
+{
+    fn fmap<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::fmap::<A, B, F>(self, f)
+    }
+}
+
+/// Functors that call the mapped function once per element, in order, so it
+/// may carry mutable state.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be mapped with an `FnMut` closure")]
+pub trait MapMutFamily<'a>: FunctorFamily<'a> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_mut<A, B, F>(fa: Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: FnMut(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+/// Functors holding at most one value, so the mapped function may consume
+/// what it captures.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be mapped with an `FnOnce` closure")]
+pub trait MapOnceFamily<'a>: MapMutFamily<'a> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_once<A, B, F>(fa: Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: FnOnce(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+/// Method syntax for `MapMutFamily`.
+pub trait MapMut<'a, A>: Functor<'a, A> + Hkt<A, Kind: MapMutFamily<'a>> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_mut<F, B>(self, f: F) -> Of<Self::Kind, B>
+    where
+        F: FnMut(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+impl<'a, T, A> MapMut<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: MapMutFamily<'a>,
+{
+    fn fmap_mut<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: FnMut(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::fmap_mut::<A, B, F>(self, f)
+    }
+}
+
+/// Method syntax for `MapOnceFamily`.
+pub trait MapOnce<'a, A>: MapMut<'a, A> + Hkt<A, Kind: MapOnceFamily<'a>> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_once<F, B>(self, f: F) -> Of<Self::Kind, B>
+    where
+        F: FnOnce(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+impl<'a, T, A> MapOnce<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: MapOnceFamily<'a>,
+{
+    fn fmap_once<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: FnOnce(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::fmap_once::<A, B, F>(self, f)
+    }
+}
+
+/// Functors that can be mapped without giving up the original structure.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be mapped by reference")]
+pub trait FunctorRefFamily<'a>: FunctorFamily<'a> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_ref<'r, A, B, F>(fa: &'r Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r;
+}
+
+/// Functors whose elements can be updated where they are. A lazy functor
+/// updates each element when it is reached, so `f` must outlive `'a`.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be mapped in place")]
+pub trait FunctorMutFamily<'a>: FunctorFamily<'a> {
+    // fmap :: (a -> a) -> f a -> f a
+    fn fmap_in_place<A, F>(fa: &mut Self::M<A>, f: F)
+    where
+        F: FnMut(&mut A) + 'a,
+        A: 'a;
+}
+
+/// Method syntax for `FunctorRefFamily`.
+pub trait FunctorRef<'a, A>: Functor<'a, A> + Hkt<A, Kind: FunctorRefFamily<'a>> {
+    // fmap :: (a -> b) -> f a -> f b
+    fn fmap_ref<'r, F, B>(&'r self, f: F) -> Of<Self::Kind, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r;
+}
+
+impl<'a, T, A> FunctorRef<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: FunctorRefFamily<'a>,
+{
+    fn fmap_ref<'r, F, B>(&'r self, f: F) -> Of<T::Kind, B>
+    where
+        F: FnMut(&'r A) -> B,
+        A: 'r,
+    {
+        T::Kind::fmap_ref::<A, B, F>(self, f)
     }
 }
 
-pub trait ApplicativeFamily {
-    type M<T>: Applicative<T, AFamily = Self>;
+/// Method syntax for `FunctorMutFamily`.
+pub trait FunctorMut<'a, A>: Functor<'a, A> + Hkt<A, Kind: FunctorMutFamily<'a>> {
+    // fmap :: (a -> a) -> f a -> f a
+    fn fmap_in_place<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut A) + 'a,
+        A: 'a;
+}
+
+impl<'a, T, A> FunctorMut<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: FunctorMutFamily<'a>,
+{
+    fn fmap_in_place<F>(&mut self, f: F)
+    where
+        F: FnMut(&mut A) + 'a,
+        A: 'a,
+    {
+        T::Kind::fmap_in_place::<A, F>(self, f)
+    }
 }
 
-pub trait Applicative<A>: Functor<A> {
-    type AFamily: ApplicativeFamily<M<A> = Self>;
+/// Structures whose elements can be visited in order, by reference.
+pub trait Foldable<A> {
+    // foldl :: (b -> a -> b) -> b -> t a -> b
+    fn fold_left<'a, B, F>(&'a self, init: B, f: F) -> B
+    where
+        F: FnMut(B, &'a A) -> B,
+        A: 'a;
+
+    // toList :: t a -> [a]
+    #[cfg(feature = "alloc")]
+    fn to_vec(&self) -> Vec<&A> {
+        self.fold_left(Vec::new(), |mut xs, a| {
+            xs.push(a);
+            xs
+        })
+    }
+
+    // length :: t a -> Int
+    fn length(&self) -> usize {
+        self.fold_left(0, |n, _| n + 1)
+    }
+}
 
+#[diagnostic::on_unimplemented(message = "`{Self}` is not an applicative functor")]
+pub trait ApplicativeFamily<'a>: FunctorFamily<'a> {
     // a -> f a
-    fn pure(a: A) -> Self;
+    fn pure<A>(a: A) -> Self::M<A>
+    where
+        A: 'a;
 
     // (a -> b -> c) -> f a -> f b -> f c
-    fn lift_a2<F, B, C>(
-        self,
-        fb: <Self::AFamily as ApplicativeFamily>::M<B>,
-        f: F,
-    ) -> <Self::AFamily as ApplicativeFamily>::M<C>
+    fn lift_a2<A, B, C, F>(fa: Self::M<A>, fb: Self::M<B>, f: F) -> Self::M<C>
     where
-        F: Fn(A, B) -> C;
+        F: Fn(A, B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+        C: 'a;
 
     // (<*>) :: f (a -> b) -> f a -> f b
-    fn apply<F, B>(
-        self,
-        func: <Self::AFamily as ApplicativeFamily>::M<F>,
-    ) -> <Self::AFamily as ApplicativeFamily>::M<B>
+    fn apply<A, B, F>(fa: Self::M<A>, func: Self::M<F>) -> Self::M<B>
     where
-        F: Fn(A) -> B,
-        Self: Sized,
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
     {
-        self.lift_a2(func, |a, f: F| f(a))
+        Self::lift_a2(fa, func, |a, f: F| f(a))
     }
 }
 
-pub trait MonadFamily {
-    type M<T>: Monad<T, MFamily = Self>;
+/// Method syntax for `ApplicativeFamily`.
+#[diagnostic::on_unimplemented(message = "`{Self}` is not an applicative functor over `{A}`")]
+pub trait Applicative<'a, A>: Functor<'a, A> + Hkt<A, Kind: ApplicativeFamily<'a>> {
+    // a -> f a
+    fn pure(a: A) -> Self
+    where
+        A: 'a;
+
+    // (a -> b -> c) -> f a -> f b -> f c
+    fn lift_a2<F, B, C>(self, fb: Of<Self::Kind, B>, f: F) -> Of<Self::Kind, C>
+    where
+        F: Fn(A, B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+        C: 'a;
+
+    // (<*>) :: f (a -> b) -> f a -> f b
+    fn apply<F, B>(self, func: Of<Self::Kind, F>) -> Of<Self::Kind, B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
 }
 
-pub trait Monad<A>: Applicative<A> {
-    type MFamily: MonadFamily<M<A> = Self>;
+impl<'a, T, A> Applicative<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: ApplicativeFamily<'a>,
+{
+    fn pure(a: A) -> Self
+    where
+        A: 'a,
+    {
+        T::Kind::pure::<A>(a)
+    }
+
+    fn lift_a2<F, B, C>(self, fb: Of<T::Kind, B>, f: F) -> Of<T::Kind, C>
+    where
+        F: Fn(A, B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        T::Kind::lift_a2::<A, B, C, F>(self, fb, f)
+    }
+
+    fn apply<F, B>(self, func: Of<T::Kind, F>) -> Of<T::Kind, B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::apply::<A, B, F>(self, func)
+    }
+}
+
+/// Selective applicative functors sit between Applicative and Monad: the
+/// effect of the function passed to `select` only needs to run when the
+/// first computation produces a `Left`, but both effects are known upfront,
+/// so conditional computations can still be statically analyzed.
+pub trait SelectiveFamily<'a>: ApplicativeFamily<'a> {
+    // select :: f (Either a b) -> f (a -> b) -> f b
+    fn select<A, B, F>(x: Self::M<Either<A, B>>, f: Self::M<F>) -> Self::M<B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a;
+
+    /// `select` for any applicative functor, which runs both effects.
+    // selectA :: f (Either a b) -> f (a -> b) -> f b
+    fn select_a<A, B, F>(x: Self::M<Either<A, B>>, f: Self::M<F>) -> Self::M<B>
+    where
+        F: Fn(A) -> B + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        Self::lift_a2(x, f, |either, f: F| match either {
+            Either::Left(a) => f(a),
+            Either::Right(b) => b,
+        })
+    }
+
+    // branch :: f (Either a b) -> f (a -> c) -> f (b -> c) -> f c
+    fn branch<A, B, C, F, G>(x: Self::M<Either<A, B>>, f: Self::M<F>, g: Self::M<G>) -> Self::M<C>
+    where
+        F: Fn(A) -> C + 'a,
+        G: Fn(B) -> C + 'a,
+        A: 'a,
+        B: 'a,
+        C: 'a,
+    {
+        let x = Self::fmap(x, |either: Either<A, B>| either.fmap(Either::<B, C>::Left));
+        let f = Self::fmap(f, |f: F| move |a| Either::Right(f(a)));
+        Self::select(Self::select(x, f), g)
+    }
+
+    // ifS :: f Bool -> f a -> f a -> f a
+    fn if_s<A>(cond: Self::M<bool>, t: Self::M<A>, e: Self::M<A>) -> Self::M<A>
+    where
+        A: Clone + 'a,
+    {
+        // Either::from maps true to Right
+        let cond = Self::fmap(cond, Either::from);
+        let t = Self::fmap(t, |a: A| move |()| a.clone());
+        let e = Self::fmap(e, |a: A| move |()| a.clone());
+        Self::branch(cond, e, t)
+    }
+
+    // whenS :: f Bool -> f () -> f ()
+    fn when_s(cond: Self::M<bool>, act: Self::M<()>) -> Self::M<()> {
+        let cond = Self::fmap(cond, |b| Either::from(b).flip());
+        Self::select(cond, Self::fmap(act, |()| |()| ()))
+    }
+}
 
+#[diagnostic::on_unimplemented(message = "`{Self}` is not a monad")]
+pub trait MonadFamily<'a>: ApplicativeFamily<'a> {
     // bind (>>=) :: m a -> (a -> m b) -> m b
-    fn bind<F, B>(self, f: F) -> <Self::MFamily as MonadFamily>::M<B>
+    fn bind<A, B, F>(ma: Self::M<A>, f: F) -> Self::M<B>
     where
-        F: Fn(A) -> <Self::MFamily as MonadFamily>::M<B>;
+        F: Fn(A) -> Self::M<B> + 'a,
+        A: 'a,
+        B: 'a;
 
     // compose (>=>) :: (a -> m b) -> (b -> m c) -> a -> m c
-    fn compose<F, G, B, C>(f: F, g: G, a: A) -> <Self::MFamily as MonadFamily>::M<C>
+    fn compose<A, B, C, F, G>(f: F, g: G, a: A) -> Self::M<C>
     where
-        F: FnOnce(A) -> <Self::MFamily as MonadFamily>::M<B>,
-        G: Fn(B) -> <Self::MFamily as MonadFamily>::M<C>,
+        F: FnOnce(A) -> Self::M<B>,
+        G: Fn(B) -> Self::M<C> + 'a,
+        B: 'a,
+        C: 'a,
     {
-        f(a).bind::<_, C>(g)
+        Self::bind(f(a), g)
     }
 
     // m (m a) -> m a
-    fn join(mma: <Self::MFamily as MonadFamily>::M<<Self::MFamily as MonadFamily>::M<A>>) -> Self
+    fn join<A>(mma: Self::M<Self::M<A>>) -> Self::M<A>
     where
-        Self: Sized,
+        A: 'a,
+        Self::M<A>: 'a,
+    {
+        Self::bind(mma, core::convert::identity)
+    }
+}
+
+/// Method syntax for `MonadFamily`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` is not a monad over `{A}`",
+    note = "every statement bound with `<-` in `mdo!` must be a monadic value like `Option` or `Vec`"
+)]
+pub trait Monad<'a, A>: Applicative<'a, A> + Hkt<A, Kind: MonadFamily<'a>> {
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind<F, B>(self, f: F) -> Of<Self::Kind, B>
+    where
+        F: Fn(A) -> Of<Self::Kind, B> + 'a,
+        A: 'a,
+        B: 'a;
+
+    // compose (>=>) :: (a -> m b) -> (b -> m c) -> a -> m c
+    fn compose<F, G, B, C>(f: F, g: G, a: A) -> Of<Self::Kind, C>
+    where
+        F: FnOnce(A) -> Of<Self::Kind, B>,
+        G: Fn(B) -> Of<Self::Kind, C> + 'a,
+        B: 'a,
+        C: 'a;
+
+    // m (m a) -> m a
+    fn join(mma: Of<Self::Kind, Self>) -> Self
+    where
+        A: 'a,
+        Self: Sized + 'a;
+}
+
+impl<'a, T, A> Monad<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: MonadFamily<'a>,
+{
+    fn bind<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: Fn(A) -> Of<T::Kind, B> + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::bind::<A, B, F>(self, f)
+    }
+
+    fn compose<F, G, B, C>(f: F, g: G, a: A) -> Of<T::Kind, C>
+    where
+        F: FnOnce(A) -> Of<T::Kind, B>,
+        G: Fn(B) -> Of<T::Kind, C> + 'a,
+        B: 'a,
+        C: 'a,
+    {
+        T::Kind::compose::<A, B, C, F, G>(f, g, a)
+    }
+
+    fn join(mma: Of<T::Kind, T>) -> T
+    where
+        A: 'a,
+        T: 'a,
+    {
+        T::Kind::join::<A>(mma)
+    }
+}
+
+/// Monads with a failure value, used for guards and refutable patterns.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no failure value for a guard or a refutable pattern"
+)]
+pub trait MonadPlusFamily<'a>: MonadFamily<'a> {
+    // mzero :: m a
+    fn mzero<A>() -> Self::M<A>
+    where
+        A: 'a;
+
+    // mplus :: m a -> m a -> m a
+    fn mplus<A>(a: Self::M<A>, b: Self::M<A>) -> Self::M<A>
+    where
+        A: 'a;
+}
+
+/// Method syntax for `MonadPlusFamily`.
+#[diagnostic::on_unimplemented(
+    message = "`{Self}` has no failure value for a guard or a refutable pattern"
+)]
+pub trait MonadPlus<'a, A>: Monad<'a, A> + Hkt<A, Kind: MonadPlusFamily<'a>> {
+    // mzero :: m a
+    fn mzero() -> Self
+    where
+        A: 'a;
+
+    // mplus :: m a -> m a -> m a
+    fn mplus(self, other: Self) -> Self
+    where
+        A: 'a;
+}
+
+impl<'a, T, A> MonadPlus<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: MonadPlusFamily<'a>,
+{
+    fn mzero() -> T
+    where
+        A: 'a,
+    {
+        T::Kind::mzero::<A>()
+    }
+
+    fn mplus(self, other: T) -> T
+    where
+        A: 'a,
+    {
+        T::Kind::mplus::<A>(self, other)
+    }
+}
+
+/// Monads that run the continuation once per value, in order, so it may
+/// carry mutable state.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be bound with an `FnMut` closure")]
+pub trait BindMutFamily<'a>: MonadFamily<'a> + MapMutFamily<'a> {
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind_mut<A, B, F>(ma: Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: FnMut(A) -> Self::M<B> + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+/// Monads holding at most one value, so the continuation may consume what
+/// it captures.
+#[diagnostic::on_unimplemented(message = "`{Self}` cannot be bound with an `FnOnce` closure")]
+pub trait BindOnceFamily<'a>: BindMutFamily<'a> + MapOnceFamily<'a> {
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind_once<A, B, F>(ma: Self::M<A>, f: F) -> Self::M<B>
+    where
+        F: FnOnce(A) -> Self::M<B> + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+/// Method syntax for `BindMutFamily`.
+pub trait BindMut<'a, A>: Monad<'a, A> + MapMut<'a, A> + Hkt<A, Kind: BindMutFamily<'a>> {
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind_mut<F, B>(self, f: F) -> Of<Self::Kind, B>
+    where
+        F: FnMut(A) -> Of<Self::Kind, B> + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+impl<'a, T, A> BindMut<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: BindMutFamily<'a>,
+{
+    fn bind_mut<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: FnMut(A) -> Of<T::Kind, B> + 'a,
+        A: 'a,
+        B: 'a,
+    {
+        T::Kind::bind_mut::<A, B, F>(self, f)
+    }
+}
+
+/// Method syntax for `BindOnceFamily`.
+pub trait BindOnce<'a, A>:
+    BindMut<'a, A> + MapOnce<'a, A> + Hkt<A, Kind: BindOnceFamily<'a>>
+{
+    // bind (>>=) :: m a -> (a -> m b) -> m b
+    fn bind_once<F, B>(self, f: F) -> Of<Self::Kind, B>
+    where
+        F: FnOnce(A) -> Of<Self::Kind, B> + 'a,
+        A: 'a,
+        B: 'a;
+}
+
+impl<'a, T, A> BindOnce<'a, A> for T
+where
+    T: Hkt<A>,
+    T::Kind: BindOnceFamily<'a>,
+{
+    fn bind_once<F, B>(self, f: F) -> Of<T::Kind, B>
+    where
+        F: FnOnce(A) -> Of<T::Kind, B> + 'a,
+        A: 'a,
+        B: 'a,
     {
-        mma.bind::<_, A>(std::convert::identity)
+        T::Kind::bind_once::<A, B, F>(self, f)
     }
 }
 
 // what trait bounds would look like
 #[allow(unused)]
-fn fmap_add<F, A>(f: F, n: usize) -> <F::FFamily as FunctorFamily>::M<A::Output>
+fn fmap_add<'a, F, A>(f: F, n: usize) -> Of<F::Kind, A::Output>
 where
-    F: Functor<A>,
-    A: std::ops::Add<usize>,
+    F: Functor<'a, A>,
+    A: core::ops::Add<usize> + 'a,
+    A::Output: 'a,
 {
-    f.fmap(|a| a + 1)
+    f.fmap(move |a| a + n)
+}
+
+pub trait Semigroup {
+    // (<>) :: a -> a -> a
+    fn combine(self, other: Self) -> Self;
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Semigroup for Vec<T> {
+    fn combine(mut self, other: Self) -> Self {
+        self.extend(other);
+        self
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Semigroup for String {
+    fn combine(self, other: Self) -> Self {
+        self + &other
+    }
+}
+
+impl Semigroup for () {
+    fn combine(self, _: Self) -> Self {}
+}
+
+pub trait Monoid: Semigroup {
+    // mempty :: a
+    fn empty() -> Self;
+}
+
+#[cfg(feature = "alloc")]
+impl<T> Monoid for Vec<T> {
+    fn empty() -> Self {
+        Vec::new()
+    }
+}
+
+#[cfg(feature = "alloc")]
+impl Monoid for String {
+    fn empty() -> Self {
+        String::new()
+    }
+}
+
+impl Monoid for () {
+    fn empty() -> Self {}
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use super::*;
+    use crate::impls::{option::OptionFamily, vec::VecFamily};
+
+    // bind and fmap share the family of `m`, so no equality bounds are needed
+    fn bind_then_show<'a, M, A, B, F>(m: M, f: F) -> Of<M::Kind, String>
+    where
+        M: Monad<'a, A>,
+        F: Fn(A) -> Of<M::Kind, B> + 'a,
+        A: 'a,
+        B: ToString + 'a,
+    {
+        m.bind::<_, B>(f).fmap(|b| b.to_string())
+    }
+
+    fn pair_with_pure<'a, K: ApplicativeFamily<'a>, A: 'a>(fa: K::M<A>) -> K::M<(A, u8)> {
+        fa.lift_a2(K::pure(0), |a, n| (a, n))
+    }
+
+    #[test]
+    fn test_kind() {
+        assert_eq!(
+            bind_then_show(Some(2), |n| Some(n * 3)),
+            Some("6".to_string())
+        );
+        assert_eq!(
+            bind_then_show(vec![1, 2], |n| vec![n, n + 10]),
+            vec!["1", "11", "2", "12"]
+        );
+
+        assert_eq!(pair_with_pure::<OptionFamily, _>(Some('a')), Some(('a', 0)));
+        assert_eq!(pair_with_pure::<VecFamily, _>(vec![1, 2]), vec![(1, 0)]);
+        assert_eq!(VecFamily::join(vec![vec![1], vec![2, 3]]), vec![1, 2, 3]);
+    }
 }
@@ -0,0 +1,558 @@
+//! Syntax sugar over the traits in `monad.rs` and `arrow.rs`.
+
+use crate::arrow::{ArrOf, Arrow, CatFamily};
+
+/// Do-notation for any type implementing `Monad`.
+///
+/// ```text
+/// mdo! {
+///     x <- Some(2);           // bind, also (a, b), _, mut x and ref x
+///     Some(n) <- Some(x.checked_mul(5));  // mzero if the pattern fails
+///     let y = n * 2;          // plain let binding
+///     guard y > 5;            // MonadPlus::mzero unless the condition holds
+///     Some(());               // bind, discarding the result
+///     pure x + y              // Applicative::pure, or any monadic expression
+/// }
+/// ```
+///
+/// Each `<-` desugars to `Monad::bind` with a closure over the rest of the
+/// block, so values used after a bind are borrowed by an `Fn` closure and
+/// must be cloned to be moved out. Variables, tuples and `_` become the
+/// parameter of the closure; a struct or enum pattern is matched instead,
+/// and as with haskell's `MonadFail`, a value that doesn't match is
+/// `MonadPlus::mzero`.
+#[macro_export]
+macro_rules! mdo {
+    (@match [$($p:tt)+] $e:expr; $($rest:tt)+) => {
+        $crate::monad::Monad::bind($e, |value| match value {
+            $($p)+ => $crate::mdo!($($rest)+),
+            #[allow(unreachable_patterns)]
+            _ => $crate::monad::MonadPlus::mzero(),
+        })
+    };
+    (let $p:pat = $e:expr; $($rest:tt)+) => {{
+        let $p = $e;
+        $crate::mdo!($($rest)+)
+    }};
+    (guard $cond:expr; $($rest:tt)+) => {
+        if $cond {
+            $crate::mdo!($($rest)+)
+        } else {
+            $crate::monad::MonadPlus::mzero()
+        }
+    };
+    (pure $e:expr $(;)?) => {
+        $crate::monad::Applicative::pure($e)
+    };
+    // `x`, `mut x`, `ref x` or `ref mut x`
+    ($($p:ident)+ <- $e:expr; $($rest:tt)+) => {
+        $crate::monad::Monad::bind($e, |$($p)+| $crate::mdo!($($rest)+))
+    };
+    // a tuple or `_`
+    ($p:tt <- $e:expr; $($rest:tt)+) => {
+        $crate::monad::Monad::bind($e, |$p| $crate::mdo!($($rest)+))
+    };
+    // a struct or enum pattern
+    ($name:ident $(:: $path:ident)* $(($($fields:tt)*))? $({ $($named:tt)* })?
+        <- $e:expr; $($rest:tt)+) => {
+        $crate::mdo!(@match [$name $(:: $path)* $(($($fields)*))? $({ $($named)* })?] $e; $($rest)+)
+    };
+    ($e:expr; $($rest:tt)+) => {
+        $crate::monad::Monad::bind($e, |_| $crate::mdo!($($rest)+))
+    };
+    ($e:expr $(;)?) => {
+        $e
+    };
+}
+
+/// List comprehensions, desugared through `mdo!`.
+///
+/// `comp![(x, y); x <- xs, y <- ys, x < y]` binds each generator in turn,
+/// treats bare expressions as guards and allows `let` bindings. Generators
+/// take the same patterns as `mdo!`, and skip the values that don't match.
+/// It works for any `MonadPlus`, though it reads best with `Vec`.
+#[macro_export]
+macro_rules! comp {
+    (@quals [$($stmts:tt)*] [$out:expr]) => {
+        $crate::mdo!($($stmts)* pure $out)
+    };
+    (@quals [$($stmts:tt)*] [$out:expr] let $p:pat = $e:expr $(, $($rest:tt)*)?) => {
+        $crate::comp!(@quals [$($stmts)* let $p = $e;] [$out] $($($rest)*)?)
+    };
+    (@quals [$($stmts:tt)*] [$out:expr] $($p:ident)+ <- $e:expr $(, $($rest:tt)*)?) => {
+        $crate::comp!(@quals [$($stmts)* $($p)+ <- $e;] [$out] $($($rest)*)?)
+    };
+    (@quals [$($stmts:tt)*] [$out:expr] $p:tt <- $e:expr $(, $($rest:tt)*)?) => {
+        $crate::comp!(@quals [$($stmts)* $p <- $e;] [$out] $($($rest)*)?)
+    };
+    (@quals [$($stmts:tt)*] [$out:expr]
+        $name:ident $(:: $path:ident)* $(($($fields:tt)*))? $({ $($named:tt)* })?
+        <- $e:expr $(, $($rest:tt)*)?) => {
+        $crate::comp!(@quals
+            [$($stmts)* $name $(:: $path)* $(($($fields)*))? $({ $($named)* })? <- $e;]
+            [$out] $($($rest)*)?)
+    };
+    (@quals [$($stmts:tt)*] [$out:expr] $cond:expr $(, $($rest:tt)*)?) => {
+        $crate::comp!(@quals [$($stmts)* guard $cond;] [$out] $($($rest)*)?)
+    };
+    ($out:expr; $($quals:tt)*) => {
+        $crate::comp!(@quals [] [$out] $($quals)*)
+    };
+}
+
+/// Arrow notation in the style of haskell's `proc` blocks, for any type
+/// implementing `Arrow`.
+///
+/// ```text
+/// proc!(input => {
+///     x <- arrow_expr -< input_expr;      // feed an arrow, bind its output
+///     let y = x + 1;                      // pure binding
+///     z <- if y > 1 {                     // ArrowChoice::owise
+///         a <- other_arrow -< y;
+///         return_a -< a
+///     } else {
+///         return_a -< 0
+///     };
+///     w <- match z {                      // owise over one branch per arm
+///         0 => { return_a -< 1 }
+///         n => { return_a -< n * 2 }
+///     };
+///     rec (total = 0) {                   // ArrowLoop::loop_d; `total` is
+///         total <- acc -< (total, w);     // last step's value until rebound
+///     }
+///     return_a -< total                   // or `arrow_expr -< expr`
+/// })
+/// ```
+///
+/// Every variable in scope is threaded through the circuit in a tuple, so
+/// bound values must be `Clone`. The type of the whole block comes from the
+/// first arrow applied with `-<`, which must come before any `if`, `match`
+/// or `rec`. The input may be annotated as `proc!(x: u32 => { .. })` when
+/// leading `let` statements need to know its type.
+#[macro_export]
+macro_rules! proc {
+    // destructure an environment, binding the oldest variables first so
+    // that newer bindings shadow them
+    (@bind $value:expr; [$p:tt]) => {
+        #[allow(unused_variables, unused_parens, irrefutable_let_patterns)]
+        let $p = $value else { unreachable!() };
+    };
+    (@bind $value:expr; [$p:tt, $($ps:tt),+]) => {
+        let (newest, rest) = $value;
+        $crate::proc!(@bind rest; [$($ps),+]);
+        #[allow(unused_variables, unused_parens, irrefutable_let_patterns)]
+        let $p = newest else { unreachable!() };
+    };
+
+    // the end of a rec block closes the loop and resumes the outer block
+    (@stmts [arrow $acc:tt] [$($env:tt),+]
+        [{ $input:ident $inits:ident ($($v:ident),+) { $($rest:tt)* } } $($frames:tt)*]) => {
+        $crate::proc!(@stmts
+            [arrow ($crate::arrow::Category::then($input, $crate::arrow::ArrowLoop::loop_d(
+                $crate::arrow::Arrow::then_pure($acc, move |env| {
+                    let fed_back = {
+                        $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                        ($($v,)+)
+                    };
+                    (env, fed_back)
+                }),
+                $inits,
+            )))]
+            [$($env),+] [$($frames)*] $($rest)*)
+    };
+    (@stmts [$($mode:tt)+] [$($env:tt),+] [$($frames:tt)*]) => {
+        compile_error!("proc! blocks must end with `return_a -< expr` or `arrow -< expr`")
+    };
+
+    // let bindings
+    (@stmts [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] let $p:pat = $e:expr; $($rest:tt)*) => {
+        $crate::proc!(@stmts
+            [pure (move |input| {
+                let env = $pre(input);
+                let value = {
+                    $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                    $e
+                };
+                (value, env)
+            })]
+            [($p), $($env),+] [$($frames)*] $($rest)*)
+    };
+    (@stmts [arrow $acc:tt] [$($env:tt),+] [$($frames:tt)*] let $p:pat = $e:expr; $($rest:tt)*) => {
+        $crate::proc!(@stmts
+            [arrow ($crate::arrow::Arrow::then_pure($acc, move |env| {
+                let value = {
+                    $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                    $e
+                };
+                (value, env)
+            }))]
+            [($p), $($env),+] [$($frames)*] $($rest)*)
+    };
+
+    // rec blocks
+    (@stmts [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] rec $($rest:tt)*) => {
+        compile_error!("proc! needs an arrow applied with `-<` before a `rec` block")
+    };
+    (@stmts [arrow $acc:tt] [$($env:tt),+] [$($frames:tt)*]
+        rec ($($v:ident = $init:expr),+ $(,)?) { $($body:tt)* } $($rest:tt)*) => {{
+        let rec_input = $acc;
+        let inits = ($($init,)+);
+        let body_input = $crate::arrow::Arrow::then_pure(
+            $crate::notation::id_pair_like(&rec_input, &inits),
+            |(env, fed_back)| (fed_back, env),
+        );
+        $crate::proc!(@stmts [arrow body_input] [($($v,)+), $($env),+]
+            [{ rec_input inits ($($v),+) { $($rest)* } } $($frames)*] $($body)*)
+    }};
+
+    // if and match bind the output of the branch that was taken
+    (@stmts [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] $p:tt <- if $($rest:tt)*) => {
+        compile_error!("proc! needs an arrow applied with `-<` before an `if`")
+    };
+    (@stmts [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] $p:tt <- match $($rest:tt)*) => {
+        compile_error!("proc! needs an arrow applied with `-<` before a `match`")
+    };
+    (@stmts [arrow $acc:tt] [$($env:tt),+] [$($frames:tt)*] $p:tt <- if $($rest:tt)*) => {
+        $crate::proc!(@if [$acc] [$($env),+] [$($frames)*] [$p] [] $($rest)*)
+    };
+    (@stmts [arrow $acc:tt] [$($env:tt),+] [$($frames:tt)*] $p:tt <- match $($rest:tt)*) => {
+        $crate::proc!(@match [$acc] [$($env),+] [$($frames)*] [$p] [] $($rest)*)
+    };
+
+    // arrow application, collecting the tokens of the arrow up to `-<`
+    (@stmts [$($mode:tt)+] [$($env:tt),+] [$($frames:tt)*] $p:tt <- $($rest:tt)*) => {
+        $crate::proc!(@apply [$($mode)+] [$($env),+] [$($frames)*] [$p] [] $($rest)*)
+    };
+    (@stmts [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] return_a -< $e:expr $(;)?) => {
+        compile_error!("proc! needs an arrow applied with `-<` before `return_a`")
+    };
+    (@stmts [arrow $acc:tt] [$($env:tt),+] [] return_a -< $e:expr $(;)?) => {
+        $crate::arrow::Arrow::then_pure($acc, move |env| {
+            $crate::proc!(@bind env; [$($env),+]);
+            $e
+        })
+    };
+    (@stmts [$($mode:tt)+] [$($env:tt),+] [$($frames:tt)*] $($rest:tt)+) => {
+        $crate::proc!(@apply [$($mode)+] [$($env),+] [$($frames)*] [_] [] $($rest)+)
+    };
+
+    (@apply [pure $pre:tt] [$($env:tt),+] [] [$p:tt] [$($f:tt)+] - < $e:expr $(;)?) => {
+        $crate::arrow::Arrow::after_pure($($f)+, move |input| {
+            let env = $pre(input);
+            $crate::proc!(@bind env; [$($env),+]);
+            $e
+        })
+    };
+    (@apply [arrow $acc:tt] [$($env:tt),+] [] [$p:tt] [$($f:tt)+] - < $e:expr $(;)?) => {
+        $crate::arrow::Category::then(
+            $crate::arrow::Arrow::then_pure($acc, move |env| {
+                $crate::proc!(@bind env; [$($env),+]);
+                $e
+            }),
+            $($f)+,
+        )
+    };
+    (@apply [pure $pre:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($f:tt)+] - < $e:expr; $($rest:tt)*) => {
+        $crate::proc!(@stmts
+            [arrow ($crate::arrow::Arrow::then_pure(
+                $crate::arrow::Arrow::after_pure($crate::arrow::Arrow::snd($($f)+), move |input| {
+                    let env = $pre(input);
+                    let value = {
+                        $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                        $e
+                    };
+                    (env, value)
+                }),
+                |(env, output)| (output, env),
+            ))]
+            [$p, $($env),+] [$($frames)*] $($rest)*)
+    };
+    (@apply [arrow $acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($f:tt)+] - < $e:expr; $($rest:tt)*) => {
+        $crate::proc!(@stmts
+            [arrow ($crate::arrow::Arrow::then_pure(
+                $crate::arrow::Category::then(
+                    $crate::arrow::Arrow::then_pure($acc, move |env| {
+                        let value = {
+                            $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                            $e
+                        };
+                        (env, value)
+                    }),
+                    $crate::arrow::Arrow::snd($($f)+),
+                ),
+                |(env, output)| (output, env),
+            ))]
+            [$p, $($env),+] [$($frames)*] $($rest)*)
+    };
+    (@apply [$($mode:tt)+] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($f:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::proc!(@apply [$($mode)+] [$($env),+] [$($frames)*] [$p] [$($f)* $t] $($rest)*)
+    };
+    (@apply [$($mode:tt)+] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($f:tt)*]) => {
+        compile_error!("expected `arrow -< expr` in proc! statement")
+    };
+
+    // collect the condition of an if up to its branches
+    (@if [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($cond:tt)+]
+        { $($on_true:tt)* } else { $($on_false:tt)* }; $($rest:tt)*) => {
+        $crate::proc!(@stmts [arrow ({
+            let input = $acc;
+            let on_true = $crate::proc!(@stmts
+                [arrow ($crate::notation::id_like(&input))] [$($env),+] [] $($on_true)*);
+            let on_false = $crate::proc!(@stmts
+                [arrow ($crate::notation::id_like(&input))] [$($env),+] [] $($on_false)*);
+            $crate::arrow::Arrow::then_pure(
+                $crate::arrow::Category::then(
+                    $crate::arrow::Arrow::then_pure(input, move |env| {
+                        let cond: bool = {
+                            $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                            $($cond)+
+                        };
+                        let branch = ::core::clone::Clone::clone(&env);
+                        let branch = match cond {
+                            true => $crate::impls::either::Either::Left(branch),
+                            false => $crate::impls::either::Either::Right(branch),
+                        };
+                        (env, branch)
+                    }),
+                    $crate::arrow::Arrow::snd($crate::arrow::ArrowChoice::owise(on_true, on_false)),
+                ),
+                |(env, output)| (output, env),
+            )
+        })] [$p, $($env),+] [$($frames)*] $($rest)*)
+    };
+    (@if [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($cond:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::proc!(@if [$acc] [$($env),+] [$($frames)*] [$p] [$($cond)* $t] $($rest)*)
+    };
+    (@if [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($cond:tt)*]) => {
+        compile_error!("expected `if cond { .. } else { .. };` in proc! statement")
+    };
+
+    // collect the scrutinee of a match up to its arms
+    (@match [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($scrut:tt)+]
+        { $($pat:pat => { $($arm:tt)* } $(,)?)+ }; $($rest:tt)*) => {
+        $crate::proc!(@stmts [arrow ({
+            let input = $crate::arrow::Arrow::then_pure($acc, move |env| {
+                let scrut = {
+                    $crate::proc!(@bind ::core::clone::Clone::clone(&env); [$($env),+]);
+                    $($scrut)+
+                };
+                (scrut, env)
+            });
+            let arms = $crate::proc!(@arms input [$($env),+] $({ ($pat) { $($arm)* } })+);
+            $crate::arrow::Arrow::then_pure(
+                $crate::arrow::Category::then(
+                    $crate::arrow::Arrow::then_pure(input, |(scrut, env)| {
+                        let env_out = ::core::clone::Clone::clone(&env);
+                        (env_out, $crate::proc!(@choose (scrut, env); $({ ($pat) })+))
+                    }),
+                    $crate::arrow::Arrow::snd(arms),
+                ),
+                |(env, output)| (output, env),
+            )
+        })] [$p, $($env),+] [$($frames)*] $($rest)*)
+    };
+    (@match [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($scrut:tt)*] $t:tt $($rest:tt)*) => {
+        $crate::proc!(@match [$acc] [$($env),+] [$($frames)*] [$p] [$($scrut)* $t] $($rest)*)
+    };
+    (@match [$acc:tt] [$($env:tt),+] [$($frames:tt)*] [$p:tt] [$($scrut:tt)*]) => {
+        compile_error!("expected `match expr { pat => { .. } .. };` in proc! statement")
+    };
+    (@arms $input:ident [$($env:tt),+] { $pat:tt { $($arm:tt)* } }) => {
+        $crate::proc!(@stmts
+            [arrow ($crate::notation::id_like(&$input))] [$pat, $($env),+] [] $($arm)*)
+    };
+    (@arms $input:ident [$($env:tt),+] { $pat:tt { $($arm:tt)* } } $($more:tt)+) => {
+        $crate::arrow::ArrowChoice::owise(
+            $crate::proc!(@arms $input [$($env),+] { $pat { $($arm)* } }),
+            $crate::proc!(@arms $input [$($env),+] $($more)+),
+        )
+    };
+    (@choose ($scrut:ident, $env:ident); { $pat:tt }) => {
+        ($scrut, $env)
+    };
+    (@choose ($scrut:ident, $env:ident); { $pat:tt } $($more:tt)+) => {{
+        #[allow(unused_variables, unused_parens)]
+        let matched = matches!(&$scrut, $pat);
+        match matched {
+            true => $crate::impls::either::Either::Left(($scrut, $env)),
+            false => $crate::impls::either::Either::Right(
+                $crate::proc!(@choose ($scrut, $env); $($more)+),
+            ),
+        }
+    }};
+
+    ($input:tt $(: $ty:ty)? => { $($body:tt)* }) => {
+        $crate::proc!(@stmts [pure (|input $(: $ty)?| input)] [$input] [] $($body)*)
+    };
+}
+
+#[doc(hidden)]
+pub fn id_like<'a, W, A, B>(_: &W) -> ArrOf<'a, W::Family, B, B>
+where
+    W: Arrow<'a, A, B>,
+    B: 'a,
+{
+    <W::Family as CatFamily<'a>>::id()
+}
+
+#[doc(hidden)]
+pub fn id_pair_like<'a, W, A, B, D>(_: &W, _: &D) -> ArrOf<'a, W::Family, (B, D), (B, D)>
+where
+    W: Arrow<'a, A, B>,
+    B: 'a,
+    D: 'a,
+{
+    <W::Family as CatFamily<'a>>::id()
+}
+
+#[cfg(all(test, feature = "alloc"))]
+mod tests {
+    use crate::impls::either::Either;
+
+    fn safe_div(a: i32, b: i32) -> Option<i32> {
+        (b != 0).then(|| a / b)
+    }
+
+    #[test]
+    fn test_mdo_option() {
+        let res: Option<i32> = mdo! {
+            x <- safe_div(100, 5);
+            let y = x + 1;
+            z <- safe_div(y, 3);
+            pure x + z
+        };
+        assert_eq!(res, Some(27));
+
+        let res: Option<i32> = mdo! {
+            x <- safe_div(1, 0);
+            pure x
+        };
+        assert_eq!(res, None);
+
+        let positive = |n: i32| -> Option<i32> {
+            mdo! {
+                guard n > 0;
+                Some(());
+                pure n
+            }
+        };
+        assert_eq!(positive(3), Some(3));
+        assert_eq!(positive(-3), None);
+
+        let res: Option<(i32, char)> = mdo! {
+            (a, b) <- Some((1, 'x'));
+            _ <- Some("ignored");
+            Some((a, b))
+        };
+        assert_eq!(res, Some((1, 'x')));
+    }
+
+    #[derive(Clone)]
+    struct Point {
+        x: i32,
+        y: i32,
+    }
+
+    #[test]
+    fn test_mdo_patterns() {
+        let res: Option<Vec<i32>> = mdo! {
+            mut xs <- Some(vec![3, 1, 2]);
+            let len = {
+                xs.sort();
+                xs.len() as i32
+            };
+            ref first <- Some(xs[0]);
+            pure xs.iter().map(|x| x * first + len).collect()
+        };
+        assert_eq!(res, Some(vec![4, 5, 6]));
+
+        // a value that doesn't match the pattern is mzero
+        let first_even = |xs: Vec<i32>| -> Option<i32> {
+            mdo! {
+                Some(x) <- Some(xs.iter().copied().find(|x| x % 2 == 0));
+                pure x
+            }
+        };
+        assert_eq!(first_even(vec![1, 4, 6]), Some(4));
+        assert_eq!(first_even(vec![1, 3]), None);
+
+        let res: Vec<i32> = mdo! {
+            Either::Right(n) <- vec![Either::Left('a'), Either::Right(1), Either::Right(2)];
+            Point { x, y: 0 } <- vec![Point { x: n, y: 0 }, Point { x: -n, y: 1 }];
+            pure x
+        };
+        assert_eq!(res, vec![1, 2]);
+
+        let res: Vec<core::cmp::Ordering> = mdo! {
+            core::cmp::Ordering::Less <- vec![1.cmp(&2), 2.cmp(&1), 0.cmp(&3)];
+            pure core::cmp::Ordering::Less
+        };
+        assert_eq!(res.len(), 2);
+    }
+
+    #[test]
+    fn test_mdo_vec() {
+        let res: Vec<(i32, i32)> = mdo! {
+            x <- vec![1, 2, 3];
+            y <- vec![10, 20];
+            guard x != 2;
+            pure (x, y)
+        };
+        assert_eq!(res, vec![(1, 10), (1, 20), (3, 10), (3, 20)]);
+    }
+
+    #[test]
+    fn test_mdo_either() {
+        let parse = |s: &str| -> Either<String, i32> {
+            match s.parse() {
+                Ok(n) => Either::Right(n),
+                Err(_) => Either::Left(format!("bad input {}", s)),
+            }
+        };
+        let sum = |a: &str, b: &str| -> Either<String, i32> {
+            mdo! {
+                x <- parse(a);
+                y <- parse(b);
+                pure x + y
+            }
+        };
+        assert_eq!(sum("1", "2"), Either::Right(3));
+        assert_eq!(sum("1", "b"), Either::Left("bad input b".to_string()));
+    }
+
+    #[test]
+    fn test_comp() {
+        let triples: Vec<_> = comp![
+            (a, b, c);
+            c <- (1..=20).collect::<Vec<u32>>(),
+            b <- (1..c).collect::<Vec<_>>(),
+            a <- (1..b).collect::<Vec<_>>(),
+            a * a + b * b == c * c
+        ];
+        assert_eq!(
+            triples,
+            vec![
+                (3, 4, 5),
+                (6, 8, 10),
+                (5, 12, 13),
+                (9, 12, 15),
+                (8, 15, 17),
+                (12, 16, 20)
+            ]
+        );
+
+        let res: Vec<_> = comp![sq; x <- vec![1, 2, 3, 4], let sq = x * x, sq % 2 == 0];
+        assert_eq!(res, vec![4, 16]);
+
+        let res: Option<_> = comp![x + 1; x <- Some(1), x > 0];
+        assert_eq!(res, Some(2));
+
+        let words = ["1", "two", "3", "-4"];
+        let res: Vec<u32> =
+            comp![n * 2; Ok(n) <- words.iter().map(|w| w.parse::<u32>()).collect::<Vec<_>>()];
+        assert_eq!(res, vec![2, 6]);
+        let res: Vec<_> =
+            comp![(*x, y); ref x <- vec![1, 2], mut y <- vec![*x; 2], { y += 1; y % 2 == 0 }];
+        assert_eq!(res, vec![(1, 2), (1, 2)]);
+    }
+}
@@ -0,0 +1,415 @@
+//! Saving the state of a circuit, to resume it later.
+//!
+//! A snapshot is a `Value` tree with the same shape as the circuit, e.g. a
+//! pair for `then` with the state of each side. Values print as a small
+//! self-describing text format, which parses back with `str::parse`:
+//!
+//! - `()` for the unit, `true` and `false`
+//! - `i` followed by an integer, e.g. `i-12`
+//! - `f` followed by a float as Rust prints it, e.g. `f0.5` or `finf`
+//! - `s` followed by the length in bytes, a colon and the text, e.g. `s2:hi`
+//! - values separated by spaces between brackets for a sequence, e.g.
+//!   `[i1 [() s0:]]`
+//!
+//! Only the static circuits take snapshots, as their state is in fields
+//! whose types are known. A `Circuit` or `SendCircuit` keeps its state in
+//! closures, which can't be inspected, so it can't be saved; build the part
+//! that needs saving as a static circuit and convert it with `into_dyn`.
+
+use alloc::{
+    boxed::Box,
+    string::{String, ToString},
+    vec,
+    vec::Vec,
+};
+use core::{fmt, str::FromStr};
+
+/// A value in a snapshot.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Value {
+    Unit,
+    Bool(bool),
+    Int(i128),
+    Float(f64),
+    Str(String),
+    Seq(Vec<Value>),
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum SnapshotError {
+    /// The text is not a snapshot, from this byte on.
+    Syntax(usize),
+    /// The snapshot doesn't fit the circuit; the value was expected to be
+    /// the one described.
+    Mismatch(&'static str),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Syntax(at) => write!(f, "invalid snapshot at byte {}", at),
+            Self::Mismatch(expected) => write!(f, "snapshot doesn't match, expected {}", expected),
+        }
+    }
+}
+
+impl core::error::Error for SnapshotError {}
+
+/// Circuits whose state can be saved and put back.
+pub trait Snapshot {
+    /// The state of the circuit as read from a snapshot.
+    type State;
+
+    fn snapshot(&self) -> Value;
+
+    /// Reads the whole state in `snapshot`, without touching the circuit.
+    fn read_state(snapshot: &Value) -> Result<Self::State, SnapshotError>;
+
+    /// Puts back a state from `read_state`.
+    fn put_state(&mut self, state: Self::State);
+
+    /// Replaces the state of the circuit with the one in `snapshot`. The
+    /// snapshot is checked first, so on an error the circuit is unchanged.
+    fn restore(&mut self, snapshot: &Value) -> Result<(), SnapshotError> {
+        let state = Self::read_state(snapshot)?;
+        self.put_state(state);
+        Ok(())
+    }
+}
+
+/// State that can be stored in a snapshot.
+pub trait Persist: Sized {
+    fn to_value(&self) -> Value;
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError>;
+}
+
+impl Value {
+    /// The two values of a pair, as stored for circuits with two parts.
+    pub fn pair(a: Value, b: Value) -> Self {
+        Self::Seq(vec![a, b])
+    }
+
+    pub fn as_pair(&self) -> Result<(&Value, &Value), SnapshotError> {
+        match self {
+            Self::Seq(items) if items.len() == 2 => Ok((&items[0], &items[1])),
+            _ => Err(SnapshotError::Mismatch("a pair")),
+        }
+    }
+
+    pub fn expect_unit(&self) -> Result<(), SnapshotError> {
+        match self {
+            Self::Unit => Ok(()),
+            _ => Err(SnapshotError::Mismatch("()")),
+        }
+    }
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Unit => write!(f, "()"),
+            Self::Bool(b) => write!(f, "{}", b),
+            Self::Int(n) => write!(f, "i{}", n),
+            Self::Float(x) => write!(f, "f{}", x),
+            Self::Str(s) => write!(f, "s{}:{}", s.len(), s),
+            Self::Seq(items) => {
+                write!(f, "[")?;
+                for (i, item) in items.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, " ")?;
+                    }
+                    write!(f, "{}", item)?;
+                }
+                write!(f, "]")
+            }
+        }
+    }
+}
+
+impl FromStr for Value {
+    type Err = SnapshotError;
+
+    fn from_str(text: &str) -> Result<Self, SnapshotError> {
+        let mut parser = Parser { text, at: 0 };
+        let value = parser.value()?;
+        if parser.at == text.len() {
+            Ok(value)
+        } else {
+            Err(SnapshotError::Syntax(parser.at))
+        }
+    }
+}
+
+struct Parser<'t> {
+    text: &'t str,
+    at: usize,
+}
+
+impl Parser<'_> {
+    fn rest(&self) -> &str {
+        &self.text[self.at..]
+    }
+
+    fn error<T>(&self) -> Result<T, SnapshotError> {
+        Err(SnapshotError::Syntax(self.at))
+    }
+
+    fn eat(&mut self, token: &str) -> bool {
+        let found = self.rest().starts_with(token);
+        if found {
+            self.at += token.len();
+        }
+        found
+    }
+
+    // the text of a number, up to the end of the value it is in
+    fn atom(&mut self) -> &str {
+        let len = self.rest().find([' ', ']']).unwrap_or(self.rest().len());
+        let atom = &self.text[self.at..self.at + len];
+        self.at += len;
+        atom
+    }
+
+    fn value(&mut self) -> Result<Value, SnapshotError> {
+        let start = self.at;
+        if self.eat("()") {
+            Ok(Value::Unit)
+        } else if self.eat("true") {
+            Ok(Value::Bool(true))
+        } else if self.eat("false") {
+            Ok(Value::Bool(false))
+        } else if self.eat("i") {
+            let n = self.atom().parse();
+            n.map(Value::Int).or(Err(SnapshotError::Syntax(start)))
+        } else if self.eat("f") {
+            let x = self.atom().parse();
+            x.map(Value::Float).or(Err(SnapshotError::Syntax(start)))
+        } else if self.eat("s") {
+            let Some((len, _)) = self.rest().split_once(':') else {
+                return self.error();
+            };
+            let Ok(n) = len.parse::<usize>() else {
+                return self.error();
+            };
+            self.at += len.len() + 1;
+            let text = self.text;
+            match text[self.at..].get(..n) {
+                Some(s) => {
+                    self.at += n;
+                    Ok(Value::Str(s.to_string()))
+                }
+                None => self.error(),
+            }
+        } else if self.eat("[") {
+            let mut items = Vec::new();
+            if self.eat("]") {
+                return Ok(Value::Seq(items));
+            }
+            loop {
+                items.push(self.value()?);
+                if self.eat("]") {
+                    return Ok(Value::Seq(items));
+                }
+                if !self.eat(" ") {
+                    return self.error();
+                }
+            }
+        } else {
+            self.error()
+        }
+    }
+}
+
+impl Persist for () {
+    fn to_value(&self) -> Value {
+        Value::Unit
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        value.expect_unit()
+    }
+}
+
+impl Persist for bool {
+    fn to_value(&self) -> Value {
+        Value::Bool(*self)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Bool(b) => Ok(*b),
+            _ => Err(SnapshotError::Mismatch("a bool")),
+        }
+    }
+}
+
+macro_rules! persist_int {
+    ($($t:ty),*) => {$(
+        impl Persist for $t {
+            fn to_value(&self) -> Value {
+                Value::Int(*self as i128)
+            }
+
+            fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+                match value {
+                    Value::Int(n) => <$t>::try_from(*n)
+                        .or(Err(SnapshotError::Mismatch(concat!("an integer in range of ", stringify!($t))))),
+                    _ => Err(SnapshotError::Mismatch("an integer")),
+                }
+            }
+        }
+    )*};
+}
+
+persist_int!(i8, i16, i32, i64, i128, isize, u8, u16, u32, u64, usize);
+
+impl Persist for f64 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Float(x) => Ok(*x),
+            _ => Err(SnapshotError::Mismatch("a float")),
+        }
+    }
+}
+
+impl Persist for f32 {
+    fn to_value(&self) -> Value {
+        Value::Float(*self as f64)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        f64::from_value(value).map(|x| x as f32)
+    }
+}
+
+impl Persist for String {
+    fn to_value(&self) -> Value {
+        Value::Str(self.clone())
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Str(s) => Ok(s.clone()),
+            _ => Err(SnapshotError::Mismatch("a string")),
+        }
+    }
+}
+
+impl<T: Persist> Persist for Box<T> {
+    fn to_value(&self) -> Value {
+        T::to_value(self)
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        T::from_value(value).map(Box::new)
+    }
+}
+
+// `None` is the empty sequence and `Some(x)` the sequence of just `x`.
+impl<T: Persist> Persist for Option<T> {
+    fn to_value(&self) -> Value {
+        Value::Seq(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Seq(items) => match items.as_slice() {
+                [] => Ok(None),
+                [x] => T::from_value(x).map(Some),
+                _ => Err(SnapshotError::Mismatch("an option")),
+            },
+            _ => Err(SnapshotError::Mismatch("an option")),
+        }
+    }
+}
+
+impl<T: Persist> Persist for Vec<T> {
+    fn to_value(&self) -> Value {
+        Value::Seq(self.iter().map(T::to_value).collect())
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Seq(items) => items.iter().map(T::from_value).collect(),
+            _ => Err(SnapshotError::Mismatch("a sequence")),
+        }
+    }
+}
+
+impl<A: Persist, B: Persist> Persist for (A, B) {
+    fn to_value(&self) -> Value {
+        Value::pair(self.0.to_value(), self.1.to_value())
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        let (a, b) = value.as_pair()?;
+        Ok((A::from_value(a)?, B::from_value(b)?))
+    }
+}
+
+impl<A: Persist, B: Persist, C: Persist> Persist for (A, B, C) {
+    fn to_value(&self) -> Value {
+        Value::Seq(vec![
+            self.0.to_value(),
+            self.1.to_value(),
+            self.2.to_value(),
+        ])
+    }
+
+    fn from_value(value: &Value) -> Result<Self, SnapshotError> {
+        match value {
+            Value::Seq(items) if items.len() == 3 => Ok((
+                A::from_value(&items[0])?,
+                B::from_value(&items[1])?,
+                C::from_value(&items[2])?,
+            )),
+            _ => Err(SnapshotError::Mismatch("a triple")),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip<T: Persist + PartialEq + fmt::Debug>(x: T) {
+        let text = x.to_value().to_string();
+        let value: Value = text.parse().unwrap();
+        assert_eq!(T::from_value(&value).unwrap(), x, "through {}", text);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        round_trip(());
+        round_trip((true, -3i64));
+        round_trip(u64::MAX);
+        round_trip((0.1f64, f64::NEG_INFINITY, -0.0f32));
+        round_trip(String::from("spaces, ] and [brackets]"));
+        round_trip(String::from("ünïcödé"));
+        round_trip(vec![Some(1u8), None, Some(3)]);
+        round_trip((Vec::<String>::new(), Box::new(Some(((), 'x' as u32)))));
+    }
+
+    #[test]
+    fn test_format() {
+        let value = (Some(-4i32), String::from("ok")).to_value();
+        assert_eq!(value.to_string(), "[[i-4] s2:ok]");
+        assert_eq!("[[i-4] s2:ok]".parse(), Ok(value));
+
+        assert_eq!("[i1  i2]".parse::<Value>(), Err(SnapshotError::Syntax(4)));
+        assert_eq!("s5:abc".parse::<Value>(), Err(SnapshotError::Syntax(3)));
+        assert_eq!("ix".parse::<Value>(), Err(SnapshotError::Syntax(0)));
+        assert_eq!("() ()".parse::<Value>(), Err(SnapshotError::Syntax(2)));
+
+        let mismatch = <(u8, bool)>::from_value(&Value::pair(Value::Int(300), Value::Bool(true)));
+        assert_eq!(
+            mismatch,
+            Err(SnapshotError::Mismatch("an integer in range of u8"))
+        );
+    }
+}
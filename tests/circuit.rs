@@ -0,0 +1,49 @@
+use arrows::{
+    arrow::*,
+    impls::circuit::{arrow, Circuit, CircuitDriver, IteratorExt},
+};
+
+fn running_mean<'a>() -> Circuit<'a, f64, f64> {
+    Circuit::accum((0.0, 0), |x, (sum, n)| {
+        let (sum, n) = (sum + x, n + 1);
+        (sum / n as f64, (sum, n))
+    })
+}
+
+#[test]
+fn test_run() {
+    let mut circ = running_mean();
+    assert_eq!(circ.run_collect([2.0, 4.0]), vec![2.0, 3.0]);
+    // the circuit keeps its state between runs
+    assert_eq!(circ.step(9.0), 5.0);
+
+    let means: Vec<_> = circ.run(vec![5.0, 5.0]).collect();
+    assert_eq!(means, vec![5.0, 5.0]);
+
+    let evens: Vec<_> = (1..=4)
+        .through(arrow(|n: u32| n * 2))
+        .filter(|n| n % 4 == 0)
+        .collect();
+    assert_eq!(evens, vec![4, 8]);
+}
+
+#[test]
+fn test_unfold() {
+    let mut fib = Circuit::unfold((0, 1), |(a, b)| (a, (b, a + b)));
+    assert_eq!(fib.run_collect([(); 7]), vec![0, 1, 1, 2, 3, 5, 8]);
+
+    let labeled = Circuit::unfold(1, |n| (n, n + 1)).dup(arrow(|c: char| c));
+    let res: Vec<_> = "abc".chars().through(labeled).collect();
+    assert_eq!(res, vec![(1, 'a'), (2, 'b'), (3, 'c')]);
+}
+
+#[test]
+fn test_driver() {
+    let mut driver = CircuitDriver::new(Circuit::accum_dup(0, |n, total| total + n));
+    // steps arrive one at a time, as from an event loop
+    for n in 1..=3 {
+        driver.step(n);
+    }
+    assert_eq!(driver.step(0), 6);
+    assert_eq!(driver.into_circuit().output(4), 10);
+}
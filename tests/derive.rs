@@ -0,0 +1,108 @@
+use arrows::{impls::either::Either, monad::*};
+
+#[derive(Functor, Foldable, Debug, PartialEq)]
+enum Tree<T> {
+    Leaf,
+    Node(Box<Tree<T>>, T, Box<Tree<T>>),
+}
+
+impl<T> Tree<T> {
+    fn node(left: Self, value: T, right: Self) -> Self {
+        Self::Node(Box::new(left), value, Box::new(right))
+    }
+
+    fn leaf(value: T) -> Self {
+        Self::node(Tree::Leaf, value, Tree::Leaf)
+    }
+}
+
+// only the last parameter is mapped over
+#[derive(Functor, Foldable, Debug, PartialEq)]
+struct Labeled<'a, L: Clone, T> {
+    label: &'a L,
+    values: Vec<T>,
+    best: Option<(usize, T)>,
+    nested: Either<String, Vec<T>>,
+}
+
+#[derive(Functor, Foldable, Debug, PartialEq)]
+struct Pair<T>(T, T);
+
+#[test]
+fn test_derive_functor() {
+    let tree = Tree::node(Tree::leaf(1), 2, Tree::leaf(3));
+    let doubled = tree.fmap(|n| n * 2);
+    assert_eq!(doubled, Tree::node(Tree::leaf(2), 4, Tree::leaf(6)));
+    assert_eq!(
+        doubled.fmap(|n| n.to_string()).to_vec(),
+        vec!["2", "4", "6"]
+    );
+
+    let label = "scores".to_string();
+    let labeled = Labeled {
+        label: &label,
+        values: vec![1, 2],
+        best: Some((1, 2)),
+        nested: Either::Right(vec![3]),
+    };
+    let mapped = labeled.fmap(|n| n as f64 / 2.0);
+    assert_eq!(mapped.label, "scores");
+    assert_eq!(mapped.values, vec![0.5, 1.0]);
+    assert_eq!(mapped.best, Some((1, 1.0)));
+    assert_eq!(mapped.nested, Either::Right(vec![1.5]));
+
+    assert_eq!(Pair(1, 2).fmap(|n| n + 1), Pair(2, 3));
+    assert_eq!(Pair('a', 'b').map_replace(0), Pair(0, 0));
+}
+
+#[test]
+fn test_derive_foldable() {
+    let tree = Tree::node(Tree::node(Tree::leaf(1), 2, Tree::Leaf), 3, Tree::leaf(4));
+    assert_eq!(tree.fold_left(0, |acc, n| acc * 10 + n), 1234);
+    assert_eq!(tree.length(), 4);
+    assert_eq!(Tree::<u8>::Leaf.length(), 0);
+
+    let label = ();
+    let labeled = Labeled {
+        label: &label,
+        values: vec!["a", "b"],
+        best: Some((7, "c")),
+        nested: Either::Right(vec!["d"]),
+    };
+    assert_eq!(labeled.to_vec(), vec![&"a", &"b", &"c", &"d"]);
+    assert_eq!(Pair(1, 2).fold_left(0, |acc, n| acc + n), 3);
+}
+
+#[test]
+fn test_derive_by_ref() {
+    let mut tree = Tree::node(Tree::leaf(1), 2, Tree::leaf(3));
+    assert_eq!(
+        tree.fmap_ref(|n| n * 2),
+        Tree::node(Tree::leaf(2), 4, Tree::leaf(6))
+    );
+    tree.fmap_in_place(|n| *n += 1);
+    assert_eq!(tree, Tree::node(Tree::leaf(2), 3, Tree::leaf(4)));
+
+    let label = "scores".to_string();
+    let mut labeled = Labeled {
+        label: &label,
+        values: vec!["a".to_string(), "bc".to_string()],
+        best: Some((1, "d".to_string())),
+        nested: Either::Right(vec!["ef".to_string()]),
+    };
+    let lengths = labeled.fmap_ref(String::len);
+    assert_eq!(lengths.label, "scores");
+    assert_eq!(lengths.values, vec![1, 2]);
+    assert_eq!(lengths.best, Some((1, 1)));
+    assert_eq!(lengths.nested, Either::Right(vec![2]));
+
+    labeled.fmap_in_place(|s| s.push('!'));
+    assert_eq!(labeled.values, vec!["a!", "bc!"]);
+    assert_eq!(labeled.best, Some((1, "d!".to_string())));
+    assert_eq!(labeled.nested, Either::Right(vec!["ef!".to_string()]));
+
+    let mut pair = Pair(1, 2);
+    assert_eq!(pair.fmap_ref(|n| n + 1), Pair(2, 3));
+    pair.fmap_in_place(|n| *n *= 10);
+    assert_eq!(pair, Pair(10, 20));
+}
@@ -0,0 +1,9 @@
+use arrows::monad::*;
+
+#[derive(Functor)]
+struct Sorted<T: Ord>(Vec<T>);
+
+#[derive(Foldable)]
+struct Callback<T>(fn(T) -> bool);
+
+fn main() {}
@@ -0,0 +1,9 @@
+use arrows::{impls::either::Either, mdo};
+
+fn main() {
+    let _: Either<(), i32> = mdo! {
+        x <- Either::Right(1);
+        guard x > 0;
+        pure x
+    };
+}
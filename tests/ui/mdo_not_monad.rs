@@ -0,0 +1,8 @@
+use arrows::mdo;
+
+fn main() {
+    let _: Option<i32> = mdo! {
+        x <- 5;
+        pure x
+    };
+}
@@ -0,0 +1,9 @@
+use arrows::{impls::either::Either, mdo};
+
+fn main() {
+    // a refutable pattern needs a failure value, which `Either` has none of
+    let _: Either<(), i32> = mdo! {
+        Some(x) <- Either::Right(Some(1));
+        pure x
+    };
+}